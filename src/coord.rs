@@ -0,0 +1,151 @@
+use crate::Sudoku;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coord {
+    pub row: u8,
+    pub col: u8,
+}
+
+impl Coord {
+    pub fn next(&self) -> Option<Self> {
+        self.next_in(Sudoku::SIZE)
+    }
+
+    /// Gets the house coordinate of the house the cell is in on a standard grid, like `get_house`
+    /// takes, so `Coord { row: 7, col: 4 }` is in the bottom-middle house `Coord { row: 2, col: 1 }`.
+    pub fn house(&self) -> Coord {
+        self.house_in(Sudoku::BOX_SIZE)
+    }
+
+    /// Gets the house coordinate of the house the cell is in on a grid with the given box size.
+    pub fn house_in(&self, box_size: u8) -> Coord {
+        Coord {
+            row: self.row / box_size,
+            col: self.col / box_size,
+        }
+    }
+
+    /// Gets the coord at an index into the 81 cells of a standard grid in row-major order, or None
+    /// past the end.
+    pub fn from_index(index: u8) -> Option<Self> {
+        (index < Sudoku::CELL_COUNT as u8).then_some(Coord {
+            row: index / Sudoku::SIZE,
+            col: index % Sudoku::SIZE,
+        })
+    }
+
+    /// Gets the index of the coord in the 81 cells of a standard grid in row-major order, the
+    /// reverse of `from_index`.
+    pub fn to_index(&self) -> u8 {
+        self.row * Sudoku::SIZE + self.col
+    }
+
+    /// Gets all 81 coords of a standard grid in row-major order, following `next`.
+    pub fn all() -> impl Iterator<Item = Coord> {
+        std::iter::successors(Some(Coord { row: 0, col: 0 }), Coord::next)
+    }
+
+    /// Returns whether two different cells of a standard grid share a row, col, or house.
+    pub fn are_peers(coord_a: Coord, coord_b: Coord) -> bool {
+        coord_a != coord_b
+            && (coord_a.row == coord_b.row
+                || coord_a.col == coord_b.col
+                || coord_a.house() == coord_b.house())
+    }
+
+    /// Gets the next coord in row-major order on a grid with `side` cells on each side.
+    pub fn next_in(&self, side: u8) -> Option<Self> {
+        let next_col = if self.col + 1 < side { self.col + 1 } else { 0 };
+        let next_row = if next_col == 0 {
+            self.row + 1
+        } else {
+            self.row
+        };
+        if next_row < side {
+            Some(Coord {
+                row: next_row,
+                col: next_col,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_coord() {
+        assert_eq!(
+            Some(Coord { row: 0, col: 1 }),
+            (Coord { row: 0, col: 0 }).next()
+        );
+        assert_eq!(
+            Some(Coord { row: 1, col: 0 }),
+            (Coord { row: 0, col: 8 }).next()
+        );
+        assert_eq!(
+            Some(Coord { row: 8, col: 0 }),
+            (Coord { row: 7, col: 8 }).next()
+        );
+        assert_eq!(None, (Coord { row: 8, col: 8 }).next());
+        assert_eq!(
+            Some(Coord { row: 1, col: 0 }),
+            (Coord { row: 0, col: 3 }).next_in(4)
+        );
+        assert_eq!(None, (Coord { row: 15, col: 15 }).next_in(16));
+    }
+
+    #[test]
+    fn test_all() {
+        assert_eq!(81, Coord::all().count());
+        assert_eq!(Some(Coord { row: 0, col: 0 }), Coord::all().next());
+        assert_eq!(Some(Coord { row: 8, col: 8 }), Coord::all().last());
+        assert_eq!(Some(Coord { row: 1, col: 0 }), Coord::all().nth(9));
+    }
+
+    #[test]
+    fn test_index() {
+        for (i, coord) in Coord::all().enumerate() {
+            assert_eq!(Some(coord), Coord::from_index(i as u8));
+            assert_eq!(i as u8, coord.to_index());
+        }
+        assert_eq!(Some(Coord { row: 1, col: 2 }), Coord::from_index(11));
+        assert_eq!(None, Coord::from_index(81));
+        assert_eq!(None, Coord::from_index(u8::MAX));
+    }
+
+    #[test]
+    fn test_house() {
+        assert_eq!(Coord { row: 1, col: 2 }, Coord { row: 4, col: 6 }.house());
+        assert_eq!(Coord { row: 0, col: 0 }, Coord { row: 2, col: 2 }.house());
+        assert_eq!(Coord { row: 2, col: 1 }, Coord { row: 7, col: 4 }.house());
+        assert_eq!(
+            Coord { row: 3, col: 0 },
+            Coord { row: 15, col: 3 }.house_in(4)
+        );
+    }
+
+    #[test]
+    fn test_are_peers() {
+        let coord = Coord { row: 4, col: 4 };
+        assert!(Coord::are_peers(coord, Coord { row: 4, col: 0 }));
+        assert!(Coord::are_peers(coord, Coord { row: 8, col: 4 }));
+        assert!(Coord::are_peers(coord, Coord { row: 3, col: 5 }));
+        assert!(!Coord::are_peers(coord, Coord { row: 0, col: 0 }));
+        assert!(!Coord::are_peers(coord, Coord { row: 2, col: 5 }));
+        assert!(!Coord::are_peers(coord, coord));
+    }
+
+    #[test]
+    fn test_size_constants() {
+        assert_eq!(9, Sudoku::SIZE);
+        assert_eq!(3, Sudoku::BOX_SIZE);
+        assert_eq!(81, Sudoku::CELL_COUNT);
+
+        assert_eq!(Sudoku::CELL_COUNT, Coord::all().count());
+    }
+}
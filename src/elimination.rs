@@ -0,0 +1,415 @@
+use crate::{Coord, Sudoku};
+
+impl Sudoku {
+    /// Rules the number out of an empty cell, returning whether it was still possible there. The
+    /// cell's restriction holds what's been ruled out, so `possible_mask` and everything built on it
+    /// sees the elimination.
+    pub(crate) fn eliminate(&mut self, coord: Coord, n: u8) -> bool {
+        if !self.is_candidate(coord, n) {
+            return false;
+        }
+
+        self.restrictions[coord.row as usize][coord.col as usize] &= !(1 << n);
+        true
+    }
+
+    /// Returns whether the number can still go in the cell, never for a filled cell.
+    pub(crate) fn is_candidate(&self, coord: Coord, n: u8) -> bool {
+        self.get(coord).is_none() && self.possible_mask(coord) & (1 << n) != 0
+    }
+
+    /// Applies the swordfish technique. When a number's possible spots in three rows all fall in the
+    /// same three cols, those rows take its place in each of the cols, so it's ruled out of the rest
+    /// of the cols. The same goes with rows and cols swapped. Returns whether anything was ruled out.
+    pub fn eliminate_swordfish(&mut self) -> bool {
+        self.eliminate_fish(3)
+    }
+
+    /// Applies the unique rectangle technique, assuming the puzzle has a unique solution. Four
+    /// empty cells at the corners of a rectangle spanning two houses can't all be down to the same
+    /// two numbers, or they could be swapped for a second solution. So when three corners are down
+    /// to the same pair and the fourth has them and more, the pair is ruled out of the fourth. On a
+    /// puzzle with more than one solution this can rule out an answer. Returns whether anything was
+    /// ruled out.
+    pub fn eliminate_unique_rectangles(&mut self) -> bool {
+        let (side, box_size) = (self.side(), self.box_size);
+        let mut eliminated = false;
+
+        for (top, bottom) in pairs(side) {
+            for (left, right) in pairs(side) {
+                // One side of the rectangle has to stay within a band or stack and the other cross it.
+                if (top / box_size == bottom / box_size) == (left / box_size == right / box_size) {
+                    continue;
+                }
+
+                let corners = [(top, left), (top, right), (bottom, left), (bottom, right)]
+                    .map(|(row, col)| Coord { row, col });
+                if corners.iter().any(|&coord| self.get(coord).is_some()) {
+                    continue;
+                }
+
+                let masks = corners.map(|coord| self.possible_mask(coord));
+                for (i, &roof) in masks.iter().enumerate() {
+                    let pair = masks[(i + 1) % 4];
+                    let floor = (0..4).filter(|&j| j != i).all(|j| masks[j] == pair);
+                    if pair.count_ones() == 2 && floor && roof & pair == pair && roof != pair {
+                        for n in Sudoku::mask_numbers(pair) {
+                            eliminated |= self.eliminate(corners[i], n);
+                        }
+                    }
+                }
+            }
+        }
+
+        eliminated
+    }
+
+    /// Applies the remote pairs technique. Empty cells down to the same two numbers that see each
+    /// other form a chain where the numbers alternate, so any two of them an odd number of links
+    /// apart hold both numbers between them. Another cell that sees both can't be either number.
+    /// Returns whether anything was ruled out.
+    pub fn eliminate_remote_pairs(&mut self) -> bool {
+        let pairs = self
+            .coords()
+            .filter(|&coord| self.get(coord).is_none())
+            .map(|coord| (coord, self.possible_mask(coord)))
+            .filter(|(_, mask)| mask.count_ones() == 2)
+            .collect::<Vec<(Coord, u32)>>();
+
+        let mut removals = Vec::new();
+        let mut chained = vec![false; pairs.len()];
+        for start in 0..pairs.len() {
+            if chained[start] {
+                continue;
+            }
+
+            // Walk the chain breadth first, noting which of the two numbers each link would hold.
+            let pair = pairs[start].1;
+            let mut chain = vec![(pairs[start].0, false)];
+            chained[start] = true;
+            let mut next = 0;
+            while let Some(&(coord, parity)) = chain.get(next) {
+                next += 1;
+                for (i, &(other, mask)) in pairs.iter().enumerate() {
+                    if !chained[i] && mask == pair && self.sees(coord, other) {
+                        chained[i] = true;
+                        chain.push((other, !parity));
+                    }
+                }
+            }
+
+            for coord in self.coords() {
+                let sees = |parity: bool| {
+                    chain
+                        .iter()
+                        .any(|&(link, p)| p == parity && self.sees(coord, link))
+                };
+                if self.possible_mask(coord) != pair && sees(false) && sees(true) {
+                    removals.extend(Sudoku::mask_numbers(pair).map(|n| (coord, n)));
+                }
+            }
+        }
+
+        let mut eliminated = false;
+        for (coord, n) in removals {
+            eliminated |= self.eliminate(coord, n);
+        }
+        eliminated
+    }
+
+    /// Applies X-cycles for the digit, following chains that alternate between strong links, the
+    /// only two spots left for the digit in a unit, and weak links, two spots that see each other.
+    /// If the digit isn't at the start of a chain then a strong link puts it at the far end, which
+    /// takes it off everything that end sees, and so on. A chain that starts and ends on a strong
+    /// link means the digit is at one end or the other, so it's ruled out of any cell that sees both
+    /// ends. That covers the eliminations of nice loops, around the loop's weak links and where two
+    /// weak links meet. A chain that comes back to where it started as the digit puts it there, so
+    /// it's ruled out of everything that cell sees. Returns whether anything was ruled out.
+    pub fn eliminate_x_cycles(&mut self, digit: u8) -> bool {
+        let spots = self
+            .coords()
+            .filter(|&coord| self.is_candidate(coord, digit))
+            .collect::<Vec<Coord>>();
+        let index = |coord: Coord| spots.iter().position(|&spot| spot == coord);
+
+        let mut strong = vec![Vec::new(); spots.len()];
+        for unit in self.units() {
+            if let [a, b] = unit
+                .iter()
+                .filter_map(|&coord| index(coord))
+                .collect::<Vec<_>>()[..]
+            {
+                strong[a].push(b);
+                strong[b].push(a);
+            }
+        }
+
+        let mut removals = Vec::new();
+        for start in 0..spots.len() {
+            // If the digit isn't at `start`, `known[spot][1]` when it has to be at the spot and
+            // `known[spot][0]` when it can't be.
+            let mut known = vec![[false; 2]; spots.len()];
+            known[start][0] = true;
+            let mut pending = vec![(start, false)];
+            while let Some((spot, is_digit)) = pending.pop() {
+                let next = match is_digit {
+                    false => strong[spot].clone(),
+                    true => (0..spots.len())
+                        .filter(|&other| self.sees(spots[spot], spots[other]))
+                        .collect(),
+                };
+                for other in next {
+                    if !known[other][!is_digit as usize] {
+                        known[other][!is_digit as usize] = true;
+                        pending.push((other, !is_digit));
+                    }
+                }
+            }
+
+            for end in (0..spots.len()).filter(|&end| known[end][1]) {
+                removals.extend(spots.iter().copied().filter(|&coord| {
+                    self.sees(coord, spots[start]) && self.sees(coord, spots[end])
+                }));
+            }
+        }
+
+        let mut eliminated = false;
+        for coord in removals {
+            eliminated |= self.eliminate(coord, digit);
+        }
+        eliminated
+    }
+
+    /// Returns whether two different cells share a row, col, or house.
+    fn sees(&self, a: Coord, b: Coord) -> bool {
+        let house = |coord: Coord| (coord.row / self.box_size, coord.col / self.box_size);
+        a != b && (a.row == b.row || a.col == b.col || house(a) == house(b))
+    }
+
+    /// Applies a fish of the given size, a swordfish for three, for each number in both directions.
+    fn eliminate_fish(&mut self, size: usize) -> bool {
+        let side = self.side();
+        let mut eliminated = false;
+
+        for n in 1..=side {
+            for transpose in [false, true] {
+                let at = |line: u8, i: u8| match transpose {
+                    false => Coord { row: line, col: i },
+                    true => Coord { row: i, col: line },
+                };
+
+                // The base lines the fish could be made of, each with its spots as a mask of indexes.
+                let lines = (0..side)
+                    .map(|line| {
+                        let spots = (0..side)
+                            .filter(|&i| self.is_candidate(at(line, i), n))
+                            .fold(0u32, |spots, i| spots | 1 << i);
+                        (line, spots)
+                    })
+                    .filter(|(_, spots)| (2..=size as u32).contains(&spots.count_ones()))
+                    .collect::<Vec<(u8, u32)>>();
+
+                for fish in combinations(lines.len(), size) {
+                    let covers = fish.iter().fold(0, |covers, &i| covers | lines[i].1);
+                    if covers.count_ones() as usize != size {
+                        continue;
+                    }
+
+                    for line in 0..side {
+                        if fish.iter().any(|&i| lines[i].0 == line) {
+                            continue;
+                        }
+                        for i in Sudoku::mask_numbers(covers) {
+                            eliminated |= self.eliminate(at(line, i), n);
+                        }
+                    }
+                }
+            }
+        }
+
+        eliminated
+    }
+}
+
+/// Gets every pair of indexes below `n`, lower first.
+fn pairs(n: u8) -> impl Iterator<Item = (u8, u8)> {
+    (0..n).flat_map(move |a| (a + 1..n).map(move |b| (a, b)))
+}
+
+/// Gets every way of picking `k` of the indexes below `n`, each in ascending order.
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+
+    (k - 1..n)
+        .flat_map(|last| {
+            combinations(last, k - 1)
+                .into_iter()
+                .map(move |mut picked| {
+                    picked.push(last);
+                    picked
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Technique;
+
+    #[test]
+    fn test_eliminate_swordfish() {
+        let sudoku = include_str!("swordfish.txt").parse::<Sudoku>().unwrap();
+        let singles = [Technique::NakedSingles, Technique::HiddenSingles];
+
+        // Singles get stuck partway, and a swordfish is what gets them going again.
+        let mut stuck = sudoku.clone();
+        stuck.propagate().unwrap();
+        assert!(!stuck.is_complete());
+        assert!(stuck.eliminate_swordfish());
+        stuck.assert_elimination_sound(&sudoku);
+        assert!(stuck.propagate().unwrap());
+
+        // So with it in the pipeline there's no guessing, which an X-Wing alone can't manage.
+        let expected = sudoku.solve().unwrap();
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::Swordfish,
+        ];
+        let (solution, counters) = sudoku.solve_with_pipeline_counted(&pipeline).unwrap();
+        assert_eq!(expected, solution);
+        assert_eq!(0, counters.placements);
+        assert!(
+            sudoku
+                .solve_with_pipeline_counted(&singles)
+                .unwrap()
+                .1
+                .placements
+                > 0
+        );
+
+        let mut x_wing = sudoku.clone();
+        while x_wing.eliminate_fish(2) | x_wing.propagate().unwrap() {}
+        assert!(!x_wing.is_complete());
+    }
+
+    #[test]
+    fn test_eliminate_remote_pairs() {
+        let sudoku = include_str!("remote_pairs.txt").parse::<Sudoku>().unwrap();
+
+        let mut stuck = sudoku.clone();
+        stuck.propagate().unwrap();
+        assert!(!stuck.is_complete());
+        assert!(stuck.eliminate_remote_pairs());
+        stuck.assert_elimination_sound(&sudoku);
+
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::RemotePairs,
+        ];
+        let (solution, counters) = sudoku.solve_with_pipeline_counted(&pipeline).unwrap();
+        assert_eq!(sudoku.solve().unwrap(), solution);
+        assert_eq!(0, counters.placements);
+
+        // A chain of four cells down to 1 and 2, zigzagging across the top houses. The ends are
+        // three links apart, so the cell that sees both of them can't be either.
+        let mut chain = Sudoku::new(vec![vec![None; 9]; 9]);
+        for (row, col) in [(0, 0), (0, 4), (1, 3), (1, 7)] {
+            chain.restrict(Coord { row, col }, [1, 2]);
+        }
+        assert!(chain.eliminate_remote_pairs());
+        let both_ends = Coord { row: 1, col: 0 };
+        assert_eq!(
+            (3..=9).collect::<Vec<u8>>(),
+            chain.get_sorted_possible_numbers(both_ends)
+        );
+        assert_eq!(
+            (1..=9).collect::<Vec<u8>>(),
+            chain.get_sorted_possible_numbers(Coord { row: 8, col: 8 })
+        );
+    }
+
+    #[test]
+    fn test_eliminate_x_cycles() {
+        let sudoku = include_str!("x_cycles.txt").parse::<Sudoku>().unwrap();
+
+        let mut stuck = sudoku.clone();
+        stuck.propagate().unwrap();
+        assert!(!stuck.is_complete());
+        assert!((1..=9).fold(false, |eliminated, digit| {
+            stuck.eliminate_x_cycles(digit) | eliminated
+        }));
+        stuck.assert_elimination_sound(&sudoku);
+
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::XCycles,
+        ];
+        let (solution, counters) = sudoku.solve_with_pipeline_counted(&pipeline).unwrap();
+        assert_eq!(sudoku.solve().unwrap(), solution);
+        assert_eq!(0, counters.placements);
+
+        // Fish alone don't get anywhere near as far.
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::Swordfish,
+        ];
+        let counters = sudoku.solve_with_pipeline_counted(&pipeline).unwrap().1;
+        assert!(counters.placements > 0);
+
+        // A skyscraper, the shortest chain: strong links along rows 0 and 4, whose ends in col 0 are
+        // weakly linked. One of the far ends, (0, 6) and (4, 7), has the 5.
+        let mut skyscraper = Sudoku::new(vec![vec![None; 9]; 9]);
+        for (row, ends) in [(0, [0, 6]), (4, [0, 7])] {
+            for col in (0..9).filter(|col| !ends.contains(col)) {
+                skyscraper.restrict(Coord { row, col }, (1..=9).filter(|&n| n != 5));
+            }
+        }
+        assert!(skyscraper.eliminate_x_cycles(5));
+        for (row, col) in [(1, 7), (2, 7), (3, 6), (5, 6)] {
+            assert!(!skyscraper.is_candidate(Coord { row, col }, 5));
+        }
+        assert!(skyscraper.is_candidate(Coord { row: 8, col: 6 }, 5));
+        assert!(skyscraper.is_candidate(Coord { row: 0, col: 6 }, 5));
+    }
+
+    #[test]
+    fn test_eliminate_unique_rectangles() {
+        let sudoku = include_str!("unique_rectangle.txt")
+            .parse::<Sudoku>()
+            .unwrap();
+
+        let mut stuck = sudoku.clone();
+        stuck.propagate().unwrap();
+        assert!(!stuck.is_complete());
+        assert!(stuck.eliminate_unique_rectangles());
+        stuck.assert_elimination_sound(&sudoku);
+
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::UniqueRectangles,
+        ];
+        let (solution, counters) = sudoku.solve_with_pipeline_counted(&pipeline).unwrap();
+        assert_eq!(sudoku.solve().unwrap(), solution);
+        assert_eq!(0, counters.placements);
+
+        // Three corners down to 2 and 9 with the fourth open, at the left of the top two bands.
+        let mut rectangle = Sudoku::new(vec![vec![None; 9]; 9]);
+        let corners = [(0, 0), (0, 1), (3, 0), (3, 1)].map(|(row, col)| Coord { row, col });
+        for &coord in &corners[..3] {
+            rectangle.restrict(coord, [2, 9]);
+        }
+        assert!(rectangle.eliminate_unique_rectangles());
+        assert_eq!(
+            (1..=8).filter(|&n| n != 2).collect::<Vec<u8>>(),
+            rectangle.get_sorted_possible_numbers(corners[3])
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    num::ParseIntError,
+};
+
+use crate::Coord;
+
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub enum InvalidSudokuError {
+    Unsolvable,
+    InvalidRow(u8),
+    InvalidCol(u8),
+    InvalidHouse(Coord),
+    InvalidRegion(u8),
+    InvalidCell(Coord),
+    InvalidAdjacency(Coord, Coord),
+    InvalidDiagonal(u8),
+    InvalidCage(u8),
+    BudgetExhausted,
+    StackLimitExceeded,
+    DuplicateGiven,
+    NeedsHarderTechnique,
+    GridTooLarge,
+    InvalidPencilMarks,
+}
+
+impl Display for InvalidSudokuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidSudokuError::Unsolvable => write!(f, "puzzle has no solution"),
+            InvalidSudokuError::InvalidRow(row) => write!(f, "row {row} is invalid"),
+            InvalidSudokuError::InvalidCol(col) => write!(f, "col {col} is invalid"),
+            InvalidSudokuError::InvalidHouse(coord) => {
+                write!(f, "house at ({}, {}) is invalid", coord.row, coord.col)
+            }
+            InvalidSudokuError::InvalidRegion(region) => write!(f, "region {region} is invalid"),
+            InvalidSudokuError::InvalidCell(coord) => write!(
+                f,
+                "cell at ({}, {}) breaks its restriction",
+                coord.row, coord.col
+            ),
+            InvalidSudokuError::InvalidAdjacency(a, b) => write!(
+                f,
+                "cells at ({}, {}) and ({}, {}) break their adjacency rule",
+                a.row, a.col, b.row, b.col
+            ),
+            InvalidSudokuError::InvalidDiagonal(diagonal) => {
+                write!(f, "diagonal {diagonal} is invalid")
+            }
+            InvalidSudokuError::InvalidCage(cage) => write!(f, "cage {cage} is invalid"),
+            InvalidSudokuError::BudgetExhausted => {
+                write!(f, "search gave up before finding a solution")
+            }
+            InvalidSudokuError::StackLimitExceeded => {
+                write!(f, "search guessed too deep before finding a solution")
+            }
+            InvalidSudokuError::DuplicateGiven => {
+                write!(
+                    f,
+                    "puzzle has the same number twice in a row, col, or house"
+                )
+            }
+            InvalidSudokuError::NeedsHarderTechnique => {
+                write!(f, "logic got stuck without a harder technique")
+            }
+            InvalidSudokuError::GridTooLarge => {
+                write!(f, "grid is too large to search all its symmetries")
+            }
+            InvalidSudokuError::InvalidPencilMarks => {
+                write!(f, "pencil marks aren't the same size as the grid")
+            }
+        }
+    }
+}
+
+impl Error for InvalidSudokuError {}
+
+#[derive(Debug)]
+pub enum ParseSudokuError {
+    ParseInt(ParseIntError),
+    InvalidSize,
+    InvalidRowLength { row: usize, len: usize },
+    InvalidValue(u8),
+    InvalidDigit(char),
+    InvalidField(&'static str),
+}
+
+impl Display for ParseSudokuError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseSudokuError::ParseInt(error) => write!(f, "couldn't parse number: {error}"),
+            ParseSudokuError::InvalidSize => write!(f, "puzzle isn't 4x4, 9x9, or 16x16"),
+            ParseSudokuError::InvalidRowLength { row, len } => {
+                write!(f, "row {row} has {len} cells, not as many as the first row")
+            }
+            ParseSudokuError::InvalidValue(n) => {
+                write!(f, "{n} isn't a number from 1 to the side of the grid")
+            }
+            ParseSudokuError::InvalidDigit(c) => write!(f, "'{c}' isn't a number or blank"),
+            ParseSudokuError::InvalidField(field) => write!(f, "{field} is invalid"),
+        }
+    }
+}
+
+impl Error for ParseSudokuError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseSudokuError::ParseInt(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Why the variant constraints attached to a puzzle can't all hold, from `validate_constraints`.
+/// Cages are numbered in the order they were added.
+#[derive(Debug, Hash, PartialEq, Eq)]
+pub enum ConstraintError {
+    CageCellOutOfBounds(u8, Coord),
+    CageRepeatedCell(u8, Coord),
+    CageSumUnreachable { cage: u8, sum: u8 },
+    AdjacencyNotAdjacent(Coord, Coord),
+    ContradictoryAdjacency(Coord, Coord),
+}
+
+impl Display for ConstraintError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstraintError::CageCellOutOfBounds(cage, coord) => write!(
+                f,
+                "cage {cage} has a cell at ({}, {}) outside the grid",
+                coord.row, coord.col
+            ),
+            ConstraintError::CageRepeatedCell(cage, coord) => write!(
+                f,
+                "cage {cage} has the cell at ({}, {}) more than once",
+                coord.row, coord.col
+            ),
+            ConstraintError::CageSumUnreachable { cage, sum } => {
+                write!(f, "cage {cage} can't add up to {sum} without repeating")
+            }
+            ConstraintError::AdjacencyNotAdjacent(a, b) => write!(
+                f,
+                "cells at ({}, {}) and ({}, {}) aren't next to each other in the grid",
+                a.row, a.col, b.row, b.col
+            ),
+            ConstraintError::ContradictoryAdjacency(a, b) => write!(
+                f,
+                "cells at ({}, {}) and ({}, {}) are both consecutive and not",
+                a.row, a.col, b.row, b.col
+            ),
+        }
+    }
+}
+
+impl Error for ConstraintError {}
+
+/// Why a line of a batch, see `solve_many`, didn't give a solution.
+#[derive(Debug)]
+pub enum BatchError {
+    Parse(ParseSudokuError),
+    Solve(InvalidSudokuError),
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatchError::Parse(error) => write!(f, "couldn't parse puzzle: {error}"),
+            BatchError::Solve(error) => write!(f, "couldn't solve puzzle: {error}"),
+        }
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BatchError::Parse(error) => Some(error),
+            BatchError::Solve(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sudoku;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "row 4 is invalid",
+            InvalidSudokuError::InvalidRow(4).to_string()
+        );
+        assert_eq!(
+            "puzzle has no solution",
+            InvalidSudokuError::Unsolvable.to_string()
+        );
+        assert_eq!(
+            "row 2 has 8 cells, not as many as the first row",
+            ParseSudokuError::InvalidRowLength { row: 2, len: 8 }.to_string()
+        );
+
+        let error = ParseSudokuError::ParseInt("x".parse::<u8>().unwrap_err());
+        assert!(error.source().is_some());
+        assert!(ParseSudokuError::InvalidSize.source().is_none());
+
+        // Both bubble up through `?` like any other error.
+        fn solve(s: &str) -> Result<Sudoku, Box<dyn Error>> {
+            Ok(s.parse::<Sudoku>()?.solve()?)
+        }
+        assert!(solve(include_str!("easy.txt")).is_ok());
+        assert_eq!(
+            "puzzle isn't 4x4, 9x9, or 16x16",
+            solve("123").unwrap_err().to_string()
+        );
+
+        let error = BatchError::Solve(InvalidSudokuError::Unsolvable);
+        assert_eq!(
+            "couldn't solve puzzle: puzzle has no solution",
+            error.to_string()
+        );
+        assert!(error.source().is_some());
+    }
+}
@@ -0,0 +1,646 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+use crate::{sudoku::BOX_SIZES, Coord, ParseSudokuError, Sudoku};
+
+/// Candidates for each cell, indexed by row then col.
+pub type PencilMarks = Vec<Vec<HashSet<u8>>>;
+
+/// Candidates of a standard grid for printing, from `candidate_grid`. Each cell is drawn as a 3x3
+/// block of its candidates, with each number in its own spot and `.` for the ones it can't be.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CandidateGrid(pub PencilMarks);
+
+impl Display for CandidateGrid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (row, cells) in self.0.iter().enumerate() {
+            if row > 0 {
+                // Bands are split by a line, rows within a band by a blank line.
+                let house = "-".repeat(13);
+                match row % 3 {
+                    0 => writeln!(f, "{house}+{house}+{}", &house[1..])?,
+                    _ => writeln!(f)?,
+                }
+            }
+
+            for line in 0..3 {
+                for (col, candidates) in cells.iter().enumerate() {
+                    if col > 0 && col % 3 == 0 {
+                        write!(f, " |")?;
+                    }
+                    write!(f, " ")?;
+                    for n in line * 3 + 1..=line * 3 + 3 {
+                        if candidates.contains(&n) {
+                            write!(f, "{n}")?;
+                        } else {
+                            write!(f, ".")?;
+                        }
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Characters used by the URL code, in order of their value.
+const URL_ALPHABET: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Length of a URL code for each box size, enough base62 digits to hold every cell as one digit of
+/// a number in base `side + 1`. A standard grid is an 81 digit base10 number.
+const URL_CODE_LENS: [(u8, usize); 3] = [(2, 7), (3, 46), (4, 176)];
+
+/// Scheme in front of the URL code in a QR payload.
+#[cfg(feature = "qr")]
+const QR_SCHEME: &str = "sudoku:";
+
+/// Converts a big number between bases, given as digits with the most significant first.
+/// Returns None if the result doesn't fit in `len` digits.
+fn convert_base(digits: &[u8], from: u32, to: u32, len: usize) -> Option<Vec<u8>> {
+    let mut digits = digits.to_vec();
+    let mut result = Vec::new();
+
+    // Repeated long division, each remainder is the next least significant digit.
+    while digits.iter().any(|&d| d != 0) {
+        let mut remainder = 0;
+        for d in digits.iter_mut() {
+            let value = remainder * from + *d as u32;
+            *d = (value / to) as u8;
+            remainder = value % to;
+        }
+        result.push(remainder as u8);
+    }
+
+    if result.len() > len {
+        return None;
+    }
+
+    result.resize(len, 0);
+    result.reverse();
+    Some(result)
+}
+
+impl Sudoku {
+    /// Parses a single line of all the cells in row-major order, with `.` or `0` for empty cells.
+    /// The size comes from the length, 16, 81, or 256 cells.
+    pub fn from_line(line: &str) -> Result<Self, ParseSudokuError> {
+        let len = line.chars().count();
+        let side = BOX_SIZES
+            .iter()
+            .map(|&box_size| box_size * box_size)
+            .find(|&side| (side as usize).pow(2) == len)
+            .ok_or(ParseSudokuError::InvalidSize)?;
+
+        let cells = line
+            .chars()
+            .map(|c| Sudoku::parse_cell(c, side))
+            .collect::<Result<Vec<Option<u8>>, ParseSudokuError>>()?;
+
+        Ok(Sudoku::new(
+            cells
+                .chunks(side as usize)
+                .map(<[Option<u8>]>::to_vec)
+                .collect(),
+        ))
+    }
+
+    /// Writes the grid as a single line of all its cells in row-major order, with `.` for empty
+    /// cells, the reverse of `from_line`.
+    pub fn to_line(&self) -> String {
+        self.grid
+            .iter()
+            .flatten()
+            .map(|&cell| Sudoku::cell_char(cell))
+            .collect()
+    }
+
+    /// Parses a line of the Sudoku Exchange puzzle bank, laid out as `id:puzzle:rating`. Returns the
+    /// puzzle along with its metadata as `id:rating`, which `to_exchange_format` takes back.
+    pub fn from_exchange_format(s: &str) -> Result<(Self, String), ParseSudokuError> {
+        let mut fields = s.trim().split(':');
+
+        let id = fields
+            .next()
+            .filter(|id| !id.is_empty())
+            .ok_or(ParseSudokuError::InvalidField("id"))?;
+        let sudoku = fields
+            .next()
+            .and_then(|puzzle| Sudoku::from_line(puzzle).ok())
+            .ok_or(ParseSudokuError::InvalidField("puzzle"))?;
+        let rating = fields
+            .next()
+            .filter(|rating| rating.parse::<f32>().is_ok())
+            .ok_or(ParseSudokuError::InvalidField("rating"))?;
+
+        if fields.next().is_some() {
+            return Err(ParseSudokuError::InvalidField("rating"));
+        }
+
+        Ok((sudoku, format!("{id}:{rating}")))
+    }
+
+    /// Writes the puzzle as a Sudoku Exchange puzzle bank line, given metadata as `id:rating`.
+    pub fn to_exchange_format(&self, metadata: &str) -> String {
+        let (id, rating) = metadata.split_once(':').unwrap_or((metadata, ""));
+        let puzzle = self
+            .grid
+            .iter()
+            .flatten()
+            .map(|&cell| cell.map_or('0', |_| Sudoku::cell_char(cell)))
+            .collect::<String>();
+
+        format!("{id}:{puzzle}:{rating}")
+    }
+
+    /// Parses a candidate export (as from Simple Sudoku), where each cell is written as its list of
+    /// candidates, with border characters (`*`, `|`, `-`, `+`) ignored. A cell with a single
+    /// candidate is taken as filled. Returns the grid along with each empty cell's candidates.
+    pub fn from_candidate_export(s: &str) -> Result<(Self, PencilMarks), ParseSudokuError> {
+        let cells = s
+            .split(|c: char| c.is_whitespace() || "*|-+".contains(c))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .chars()
+                    .map(|c| match c.to_string().parse::<u8>() {
+                        Ok(0) => Err(ParseSudokuError::InvalidValue(0)),
+                        Ok(n) => Ok(n),
+                        Err(error) => Err(ParseSudokuError::ParseInt(error)),
+                    })
+                    .collect::<Result<HashSet<u8>, ParseSudokuError>>()
+            })
+            .collect::<Result<Vec<HashSet<u8>>, ParseSudokuError>>()?;
+
+        if cells.len() != Sudoku::CELL_COUNT {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        let size = Sudoku::SIZE as usize;
+        let mut grid = vec![vec![None; size]; size];
+        let mut marks = vec![vec![HashSet::new(); size]; size];
+        for (i, candidates) in cells.into_iter().enumerate() {
+            if candidates.len() == 1 {
+                grid[i / size][i % size] = candidates.into_iter().next();
+            } else {
+                marks[i / size][i % size] = candidates;
+            }
+        }
+
+        Ok((Sudoku::new(grid), marks))
+    }
+
+    /// Gets the possible numbers of every cell, indexed by row then col. Filled cells have none.
+    pub fn candidates(&self) -> PencilMarks {
+        let side = self.side();
+        (0..side)
+            .map(|row| {
+                (0..side)
+                    .map(|col| self.get_possible_numbers(Coord { row, col }))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Gets the candidates of every cell ready to print, see `CandidateGrid`.
+    pub fn candidate_grid(&self) -> CandidateGrid {
+        CandidateGrid(self.candidates())
+    }
+
+    /// Gets a stable representation of the grid for comparing in tests, nine rows of nine cells
+    /// with `.` for empty cells, separated by `\n` with no trailing whitespace.
+    pub fn to_canonical_string(&self) -> String {
+        self.to_string().lines().collect::<Vec<&str>>().join("\n")
+    }
+
+    /// Gets each row as a string of its cells, with `.` for empty cells, ready to be drawn a row at
+    /// a time.
+    pub fn rows_as_strings(&self) -> Vec<String> {
+        self.grid
+            .iter()
+            .map(|row| row.iter().map(|&cell| Sudoku::cell_char(cell)).collect())
+            .collect()
+    }
+
+    /// Encodes the grid as a short, URL-safe code. The cells are read as one big number in base
+    /// `side + 1` (0 for empty cells, so base10 for a standard grid) and written out in base62. Each
+    /// size has its own code length, which is how `from_url_code` tells them apart.
+    pub fn to_url_code(&self) -> String {
+        let cells = self
+            .grid
+            .iter()
+            .flatten()
+            .map(|cell| cell.unwrap_or(0))
+            .collect::<Vec<u8>>();
+        let len = URL_CODE_LENS
+            .iter()
+            .find(|&&(box_size, _)| box_size == self.box_size)
+            .map_or(0, |&(_, len)| len);
+
+        // Every cell is below the base, so the code always fits.
+        convert_base(&cells, self.side() as u32 + 1, 62, len)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|d| URL_ALPHABET[d as usize] as char)
+            .collect()
+    }
+
+    /// Decodes a grid from a code made by `to_url_code`.
+    pub fn from_url_code(code: &str) -> Result<Self, ParseSudokuError> {
+        let &(box_size, _) = URL_CODE_LENS
+            .iter()
+            .find(|&&(_, len)| len == code.len())
+            .ok_or(ParseSudokuError::InvalidSize)?;
+        let side = box_size * box_size;
+
+        let digits = code
+            .bytes()
+            .map(|b| {
+                URL_ALPHABET
+                    .iter()
+                    .position(|&c| c == b)
+                    .map(|d| d as u8)
+                    .ok_or(ParseSudokuError::InvalidValue(b))
+            })
+            .collect::<Result<Vec<u8>, ParseSudokuError>>()?;
+
+        let cells = convert_base(&digits, 62, side as u32 + 1, (side as usize).pow(2))
+            .ok_or(ParseSudokuError::InvalidSize)?;
+
+        Ok(Sudoku::new(
+            cells
+                .chunks(side as usize)
+                .map(|row| row.iter().map(|&n| (n != 0).then_some(n)).collect())
+                .collect(),
+        ))
+    }
+
+    /// Gets a short payload for sharing the puzzle as a QR code, the URL code from `to_url_code`
+    /// behind a `sudoku:` scheme.
+    #[cfg(feature = "qr")]
+    pub fn to_qr_payload(&self) -> String {
+        format!("{QR_SCHEME}{}", self.to_url_code())
+    }
+
+    /// Decodes a grid from a payload made by `to_qr_payload`, checking it has the `sudoku:` scheme.
+    #[cfg(feature = "qr")]
+    pub fn from_qr_payload(payload: &str) -> Result<Self, ParseSudokuError> {
+        let code = payload
+            .strip_prefix(QR_SCHEME)
+            .ok_or(ParseSudokuError::InvalidField("scheme"))?;
+        Sudoku::from_url_code(code)
+    }
+
+    /// Returns whether every cell of a grid with this side length fits in a nibble, 0 to 15.
+    fn packs_nibbles(side: u8) -> bool {
+        side < 16
+    }
+
+    /// Packs the grid into bytes, a header byte with the side length followed by two cells per byte
+    /// (high nibble first), with 0 for an empty cell. A 16x16 grid's 16s don't fit in a nibble, so
+    /// it gets a byte per cell instead.
+    pub fn to_compact_bytes(&self) -> Vec<u8> {
+        let cells = self
+            .grid
+            .iter()
+            .flatten()
+            .map(|cell| cell.unwrap_or(0))
+            .collect::<Vec<u8>>();
+
+        let mut bytes = vec![self.side()];
+        if Sudoku::packs_nibbles(self.side()) {
+            bytes.extend(
+                cells
+                    .chunks(2)
+                    .map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)),
+            );
+        } else {
+            bytes.extend(cells);
+        }
+
+        bytes
+    }
+
+    /// Unpacks a grid packed by `to_compact_bytes`.
+    pub fn from_compact_bytes(bytes: &[u8]) -> Result<Self, ParseSudokuError> {
+        let Some((&side, packed)) = bytes.split_first() else {
+            return Err(ParseSudokuError::InvalidSize);
+        };
+        if !BOX_SIZES
+            .iter()
+            .any(|&box_size| box_size * box_size == side)
+        {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        let cell_count = (side as usize).pow(2);
+        let cells = if Sudoku::packs_nibbles(side) {
+            packed
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0xF])
+                .collect::<Vec<u8>>()
+        } else {
+            packed.to_vec()
+        };
+        if cells.len() != cell_count + cell_count % 2 {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        let cells = cells
+            .into_iter()
+            .take(cell_count)
+            .map(|n| match n {
+                0 => Ok(None),
+                n if n <= side => Ok(Some(n)),
+                _ => Err(ParseSudokuError::InvalidValue(n)),
+            })
+            .collect::<Result<Vec<Option<u8>>, ParseSudokuError>>()?;
+
+        Ok(Sudoku::new(
+            cells
+                .chunks(side as usize)
+                .map(<[Option<u8>]>::to_vec)
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Sudoku {
+        /// Encodes the grid to every format and decodes it back, checking each gives the same grid.
+        fn assert_all_formats_roundtrip(&self) {
+            let parse = |s: &str| s.parse::<Sudoku>().unwrap();
+            assert_eq!(*self, parse(&self.to_string()), "grid");
+            assert_eq!(*self, Sudoku::from_line(&self.to_line()).unwrap(), "line");
+            assert_eq!(*self, parse(&self.to_canonical_string()), "canonical");
+            assert_eq!(*self, parse(&self.rows_as_strings().join("\n")), "rows");
+            assert_eq!(
+                *self,
+                Sudoku::from_exchange_format(&self.to_exchange_format("id:1.0"))
+                    .unwrap()
+                    .0,
+                "exchange"
+            );
+            if let Some(array) = self.to_array() {
+                assert_eq!(*self, Sudoku::from_array(array).unwrap(), "array");
+            }
+            assert_eq!(
+                *self,
+                Sudoku::from_grid(self.grid.clone()).unwrap(),
+                "grid rows"
+            );
+            assert_eq!(
+                *self,
+                Sudoku::from_url_code(&self.to_url_code()).unwrap(),
+                "url code"
+            );
+            assert_eq!(
+                *self,
+                Sudoku::from_compact_bytes(&self.to_compact_bytes()).unwrap(),
+                "compact bytes"
+            );
+            #[cfg(feature = "serde")]
+            assert_eq!(
+                *self,
+                serde_json::from_str::<Sudoku>(&serde_json::to_string(self).unwrap()).unwrap(),
+                "json"
+            );
+            #[cfg(feature = "qr")]
+            assert_eq!(
+                *self,
+                Sudoku::from_qr_payload(&self.to_qr_payload()).unwrap(),
+                "qr"
+            );
+        }
+    }
+
+    #[test]
+    fn test_assert_all_formats_roundtrip() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        sudoku.assert_all_formats_roundtrip();
+        sudoku.solve().unwrap().assert_all_formats_roundtrip();
+        Sudoku::from_array([[None; 9]; 9])
+            .unwrap()
+            .assert_all_formats_roundtrip();
+
+        let mut sparse = sudoku.clone();
+        for coord in Coord::all().filter(|coord| coord.row % 2 == 0) {
+            sparse.unset(coord);
+        }
+        sparse.assert_all_formats_roundtrip();
+
+        for box_size in [2, 4] {
+            let solved = Sudoku::base_pattern(box_size);
+            solved.assert_all_formats_roundtrip();
+
+            let mut sparse = solved.clone();
+            for coord in solved.coords().filter(|coord| coord.row % 2 == 0) {
+                sparse.unset(coord);
+            }
+            sparse.assert_all_formats_roundtrip();
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let candidates = sudoku.candidates();
+        assert_eq!(HashSet::from([1, 2, 3, 4, 8]), candidates[0][0]);
+        assert!(candidates[0][1].is_empty());
+        for coord in Coord::all().filter(|&coord| sudoku.get(coord).is_none()) {
+            assert_eq!(
+                sudoku.get_possible_numbers(coord),
+                candidates[coord.row as usize][coord.col as usize]
+            );
+        }
+
+        let grid = sudoku.candidate_grid().to_string();
+        let lines = grid.lines().collect::<Vec<&str>>();
+        assert_eq!(9 * 3 + 8, lines.len());
+        assert_eq!(" 123 ... ", &lines[0][..9]);
+        assert_eq!(" 4.. ... ", &lines[1][..9]);
+        assert_eq!(" .8. ... ", &lines[2][..9]);
+        assert!(lines[11].starts_with("-------------+"));
+    }
+
+    #[test]
+    fn test_to_line() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let line = solved.to_line();
+        assert_eq!(81, line.len());
+        assert!(line.starts_with("894137526235"));
+        assert_eq!(solved, Sudoku::from_line(&line).unwrap());
+
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.to_line().starts_with(".9......6...."));
+        assert_eq!(sudoku, sudoku.to_line().parse::<Sudoku>().unwrap());
+    }
+
+    #[test]
+    fn test_compact_bytes() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let bytes = sudoku.to_compact_bytes();
+        assert_eq!(42, bytes.len());
+        assert_eq!(sudoku, Sudoku::from_compact_bytes(&bytes).unwrap());
+
+        assert!(Sudoku::from_compact_bytes(&bytes[..41]).is_err());
+
+        // 16s take a whole byte, without running into the cell next to them.
+        let sixteen = Sudoku::base_pattern(4);
+        let bytes = sixteen.to_compact_bytes();
+        assert_eq!(257, bytes.len());
+        assert_eq!(16, bytes[0]);
+        assert_eq!(sixteen, Sudoku::from_compact_bytes(&bytes).unwrap());
+
+        let four = Sudoku::base_pattern(2);
+        let bytes = four.to_compact_bytes();
+        assert_eq!(vec![4, 0x12, 0x34], bytes[..3]);
+        assert_eq!(9, bytes.len());
+        assert_eq!(four, Sudoku::from_compact_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_canonical_string() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        for _ in 0..5 {
+            let result = sudoku.solve().unwrap().to_canonical_string();
+            assert_eq!(expected.to_canonical_string(), result);
+            assert_eq!(89, result.len());
+            assert!(!result.ends_with('\n'));
+        }
+    }
+
+    #[test]
+    fn test_rows_as_strings() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let expected = vec![
+            "894137526",
+            "235468791",
+            "167592438",
+            "942681375",
+            "576329814",
+            "381754269",
+            "658213947",
+            "729846153",
+            "413975682",
+        ];
+        assert_eq!(expected, solved.rows_as_strings());
+
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(".9......6", sudoku.rows_as_strings()[0]);
+
+        let four = Sudoku::base_pattern(2);
+        assert_eq!(vec!["1234", "3412", "2341", "4123"], four.rows_as_strings());
+        let sixteen = Sudoku::base_pattern(4);
+        assert_eq!("123456789abcdefg", sixteen.rows_as_strings()[0]);
+    }
+
+    #[test]
+    fn test_url_code() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let code = sudoku.to_url_code();
+        assert_eq!(46, code.len());
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_eq!(sudoku, Sudoku::from_url_code(&code).unwrap());
+
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(
+            solved,
+            Sudoku::from_url_code(&solved.to_url_code()).unwrap()
+        );
+
+        assert!(Sudoku::from_url_code("not a code").is_err());
+        assert!(Sudoku::from_url_code(&"z".repeat(46)).is_err());
+
+        // Each size has its own length, so a code always comes back the size it went in.
+        for (box_size, len) in URL_CODE_LENS {
+            let solved = Sudoku::base_pattern(box_size);
+            let code = solved.to_url_code();
+            assert_eq!(len, code.len());
+            assert_eq!(solved, Sudoku::from_url_code(&code).unwrap());
+        }
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_qr_payload() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let payload = sudoku.to_qr_payload();
+        assert_eq!(format!("sudoku:{}", sudoku.to_url_code()), payload);
+        assert_eq!(sudoku, Sudoku::from_qr_payload(&payload).unwrap());
+
+        assert!(matches!(
+            Sudoku::from_qr_payload(&payload.replace("sudoku:", "http:")),
+            Err(ParseSudokuError::InvalidField("scheme"))
+        ));
+        assert!(Sudoku::from_qr_payload("sudoku:not a code").is_err());
+    }
+
+    #[test]
+    fn test_exchange_format() {
+        let line = "0000183b305c:050703060007000800000816000000030000005000100730040086906000204840572093000409000:1.2";
+        let (sudoku, metadata) = Sudoku::from_exchange_format(line).unwrap();
+        assert_eq!("0000183b305c:1.2", metadata);
+        assert_eq!(Some(5), sudoku.get(Coord { row: 0, col: 1 }));
+        assert_eq!(None, sudoku.get(Coord { row: 0, col: 0 }));
+        assert_eq!(line, sudoku.to_exchange_format(&metadata));
+
+        assert!(matches!(
+            Sudoku::from_exchange_format(":050703:1.2"),
+            Err(ParseSudokuError::InvalidField("id"))
+        ));
+        assert!(matches!(
+            Sudoku::from_exchange_format("abc:050703:1.2"),
+            Err(ParseSudokuError::InvalidField("puzzle"))
+        ));
+        assert!(matches!(
+            Sudoku::from_exchange_format(&line.replace(":1.2", ":hard")),
+            Err(ParseSudokuError::InvalidField("rating"))
+        ));
+    }
+
+    #[test]
+    fn test_from_candidate_export() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+
+        // Build an export out of the easy puzzle's candidates.
+        let mut export = String::from("*-----------*\n");
+        for row in 0..9 {
+            export.push('|');
+            for col in 0..9 {
+                let coord = Coord { row, col };
+                let mut candidates = match sudoku.get(coord) {
+                    Some(n) => vec![n],
+                    None => sudoku.get_possible_numbers(coord).into_iter().collect(),
+                };
+                candidates.sort();
+                let cell = candidates.iter().map(u8::to_string).collect::<String>();
+                export.push_str(&format!(" {cell:<9}"));
+                if col % 3 == 2 {
+                    export.push('|');
+                }
+            }
+            export.push('\n');
+        }
+        export.push_str("*-----------*\n");
+
+        // Empty cells down to one candidate come back as filled, so only the givens match up.
+        let (result, marks) = Sudoku::from_candidate_export(&export).unwrap();
+        assert!(sudoku.mistakes(&result).is_empty());
+        assert_eq!(sudoku.solve().unwrap(), result.solve().unwrap());
+        let expected: HashSet<u8> = vec![1, 2, 3, 4, 8].into_iter().collect();
+        assert_eq!(expected, marks[0][0]);
+        assert!(marks[0][1].is_empty());
+
+        assert!(Sudoku::from_candidate_export("12 3 4").is_err());
+        assert!(Sudoku::from_candidate_export(&export.replace("1", "x")).is_err());
+    }
+}
@@ -0,0 +1,26 @@
+mod coord;
+mod elimination;
+mod error;
+mod format;
+mod logic;
+mod rng;
+mod search;
+#[cfg(feature = "serde")]
+mod serialize;
+mod sudoku;
+mod symmetry;
+mod unit;
+mod variant;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use coord::Coord;
+pub use error::{BatchError, ConstraintError, InvalidSudokuError, ParseSudokuError};
+pub use format::{CandidateGrid, PencilMarks};
+pub use logic::{Certificate, Difficulty, HiddenPair, Hint, HintReason, SolveIterator, Technique};
+pub use search::{BatchStats, PuzzleStatus};
+pub use sudoku::{PerfCounters, Sudoku};
+pub use unit::Unit;
+pub use variant::{Adjacency, Cage, Variant};
+#[cfg(feature = "wasm")]
+pub use wasm::solve_string;
@@ -0,0 +1,1660 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Coord, InvalidSudokuError, PerfCounters, Sudoku, Unit};
+
+/// Ordered placements that take a puzzle to its solution, checkable with `verify_certificate`.
+/// Each step says why it was forced, or `None` for a guess taken from the solution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    pub steps: Vec<(Coord, u8, Option<HintReason>)>,
+}
+
+/// A placement from `hint`: the cell, its number, and why logic forces it.
+pub type Hint = (Coord, u8, HintReason);
+
+/// A hidden pair from `find_hidden_pairs`: the unit, its two cells, and its two numbers.
+pub type HiddenPair = (Unit, (Coord, Coord), (u8, u8));
+
+/// How hard a puzzle is, from the techniques it takes to solve.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Difficulty {
+    /// Already solved, nothing to fill.
+    Trivial,
+    /// Solvable with naked singles alone.
+    Easy,
+    /// Needs hidden singles as well.
+    Medium,
+    /// Needs guessing.
+    Hard,
+}
+
+/// Why `hint` picked the placement it did.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum HintReason {
+    /// The number is the only one possible in the cell.
+    NakedSingle,
+    /// The cell is the only spot left for the number in one of its units.
+    HiddenSingle,
+}
+
+/// A way of filling cells without guessing, for `solve_with_pipeline`. They're ordered easiest
+/// first, like `Technique::ALL`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Technique {
+    /// Fills any cell with only one possible number.
+    NakedSingles,
+    /// Fills any number with only one possible spot left in its house.
+    HiddenSingles,
+    /// Rules out numbers with `eliminate_swordfish`.
+    Swordfish,
+    /// Rules out numbers with `eliminate_remote_pairs`.
+    RemotePairs,
+    /// Rules out numbers with `eliminate_unique_rectangles`, assuming a unique solution. Pipelines
+    /// skip it for puzzles that don't have one, where it could rule out real solutions.
+    UniqueRectangles,
+    /// Rules out numbers with `eliminate_x_cycles`, for every digit.
+    XCycles,
+}
+
+impl Technique {
+    /// Every technique, easiest first.
+    pub const ALL: [Technique; 6] = [
+        Technique::NakedSingles,
+        Technique::HiddenSingles,
+        Technique::Swordfish,
+        Technique::RemotePairs,
+        Technique::UniqueRectangles,
+        Technique::XCycles,
+    ];
+}
+
+/// Yields the placements that solve a puzzle one at a time, from `solve_steps`.
+#[derive(Clone, Debug)]
+pub struct SolveIterator {
+    sudoku: Sudoku,
+    /// Worked out the first time logic gets stuck, `Some(None)` if there's no solution.
+    solution: Option<Option<Sudoku>>,
+}
+
+impl Iterator for SolveIterator {
+    type Item = (Coord, u8);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (coord, n) = match self.sudoku.hint() {
+            Some((coord, n, _)) => (coord, n),
+            None => {
+                let coord = self.sudoku.most_constrained_cell()?;
+                let sudoku = &self.sudoku;
+                let solution = self
+                    .solution
+                    .get_or_insert_with(|| sudoku.solve().ok())
+                    .as_ref()?;
+                (coord, solution.get(coord)?)
+            }
+        };
+
+        self.sudoku.set(coord, n);
+        Some((coord, n))
+    }
+}
+
+impl Sudoku {
+    /// Level in `difficulty_heatmap` for cells that had to be guessed, the hardest there is.
+    pub const GUESSED_LEVEL: u8 = 3;
+
+    /// Repeatedly fills any empty cell that only has one possible number, until no more can be filled.
+    /// Returns the cells that were filled, in order.
+    pub(crate) fn fill_singles(&mut self) -> Vec<(Coord, u8)> {
+        let mut filled = Vec::new();
+
+        loop {
+            let mut progress = false;
+
+            for coord in self.coords().collect::<Vec<_>>() {
+                if self.get(coord).is_some() {
+                    continue;
+                }
+
+                let possible = self.get_possible_numbers(coord);
+                if possible.len() == 1 {
+                    if let Some(n) = possible.into_iter().next() {
+                        self.set(coord, n);
+                        filled.push((coord, n));
+                        progress = true;
+                    }
+                }
+            }
+
+            if !progress {
+                return filled;
+            }
+        }
+    }
+
+    /// Repeatedly fills any empty cell that only has one possible number, and any number that only
+    /// has one possible spot in a unit, until no more can be filled. Returns whether anything was
+    /// filled, or `Unsolvable` if an empty cell is left with no possible numbers.
+    pub fn propagate(&mut self) -> Result<bool, InvalidSudokuError> {
+        let mut filled = false;
+
+        loop {
+            let progress = !self.fill_singles().is_empty() | self.apply_hidden_singles();
+            if !progress {
+                break;
+            }
+            filled = true;
+        }
+
+        if self.has_dead_end() {
+            return Err(InvalidSudokuError::Unsolvable);
+        }
+
+        Ok(filled)
+    }
+
+    /// Gets the next placement logic can make without guessing, a naked single if there is one and
+    /// otherwise a hidden single. Returns `None` if the puzzle would need a guess.
+    pub fn hint(&self) -> Option<Hint> {
+        let naked = self
+            .coords()
+            .filter(|&coord| self.get(coord).is_none())
+            .find_map(|coord| {
+                let mask = self.possible_mask(coord);
+                (mask.count_ones() == 1)
+                    .then(|| (coord, mask.trailing_zeros() as u8, HintReason::NakedSingle))
+            });
+        if naked.is_some() {
+            return naked;
+        }
+
+        self.units().into_iter().find_map(|unit| {
+            (1..=self.side()).find_map(|n| {
+                let mut spots = unit.iter().filter(|&&coord| {
+                    self.get(coord).is_none() && self.possible_mask(coord) & (1 << n) != 0
+                });
+                match (spots.next(), spots.next()) {
+                    (Some(&coord), None) => Some((coord, n, HintReason::HiddenSingle)),
+                    _ => None,
+                }
+            })
+        })
+    }
+
+    /// Gets up to `count` hints in a row, each one what `hint` gives once the ones before it are
+    /// placed, stopping early if only a guess would make progress. The grid isn't changed.
+    pub fn hint_sequence(&self, count: usize) -> Vec<Hint> {
+        let mut sudoku = self.clone();
+        std::iter::from_fn(|| {
+            let hint = sudoku.hint()?;
+            sudoku.set(hint.0, hint.1);
+            Some(hint)
+        })
+        .take(count)
+        .collect()
+    }
+
+    /// Places every number that only has one possible spot left in a row, col, or house. Returns
+    /// whether anything was placed.
+    pub fn apply_hidden_singles(&mut self) -> bool {
+        let mut progress = false;
+
+        for unit in self.units() {
+            // Placing a number only ever takes possibilities away, so these can be more than are
+            // left but never fewer. Anything found is checked again before it's placed.
+            let masks = unit
+                .iter()
+                .filter(|&&coord| self.get(coord).is_none())
+                .map(|&coord| (coord, self.possible_mask(coord)))
+                .collect::<Vec<(Coord, u32)>>();
+            let missing = masks.iter().fold(0, |missing, (_, mask)| missing | mask);
+
+            for n in Sudoku::mask_numbers(missing) {
+                let mut spots = masks.iter().filter(|(_, mask)| mask & (1 << n) != 0);
+                if let (Some(&(coord, _)), None) = (spots.next(), spots.next()) {
+                    if self.get(coord).is_none() && self.possible_mask(coord) & (1 << n) != 0 {
+                        self.set(coord, n);
+                        progress = true;
+                    }
+                }
+            }
+        }
+
+        progress
+    }
+
+    /// Runs AC-3 over the all-different constraints between peers, each cell's domain starting as
+    /// its possible numbers. A number only loses its support in a peer once that peer's domain is
+    /// down to that one number, and each pruned cell queues its peers' arcs to be revised again.
+    /// Cells left with one number are filled. Returns false on a contradiction, a repeated number
+    /// or a cell whose domain empties.
+    pub fn enforce_arc_consistency(&mut self) -> bool {
+        if self.has_duplicates() {
+            return false;
+        }
+
+        let side = self.side() as usize;
+        let index = |coord: Coord| coord.row as usize * side + coord.col as usize;
+        let coords = self.coords().collect::<Vec<Coord>>();
+        let mut domains = coords
+            .iter()
+            .map(|&coord| match self.get(coord) {
+                Some(n) => 1 << n,
+                None => self.possible_mask(coord),
+            })
+            .collect::<Vec<u32>>();
+
+        let mut units = self.units();
+        if self.diagonals {
+            units.extend(self.diagonal_coords());
+        }
+        let mut peers = vec![Vec::new(); coords.len()];
+        for unit in &units {
+            for &a in unit {
+                peers[index(a)].extend(unit.iter().filter(|&&b| b != a).map(|&b| index(b)));
+            }
+        }
+
+        let mut arcs = (0..coords.len())
+            .flat_map(|i| peers[i].iter().map(move |&j| (i, j)))
+            .collect::<VecDeque<(usize, usize)>>();
+        while let Some((i, j)) = arcs.pop_front() {
+            if domains[j].count_ones() != 1 || domains[i] & domains[j] == 0 {
+                continue;
+            }
+
+            domains[i] &= !domains[j];
+            if domains[i] == 0 {
+                return false;
+            }
+            arcs.extend(peers[i].iter().filter(|&&k| k != j).map(|&k| (k, i)));
+        }
+
+        for (&coord, domain) in coords.iter().zip(domains) {
+            if self.get(coord).is_none() && domain.count_ones() == 1 {
+                self.set(coord, domain.trailing_zeros() as u8);
+            }
+        }
+
+        true
+    }
+
+    /// Solves by applying the techniques in the given order to a fixpoint before each guess, going
+    /// back to the first technique whenever one fills something. An empty pipeline is plain
+    /// backtracking. `UniqueRectangles` is left out unless the puzzle has a unique solution.
+    pub fn solve_with_pipeline(
+        &self,
+        pipeline: &[Technique],
+    ) -> Result<Sudoku, InvalidSudokuError> {
+        self.solve_with_pipeline_counted(pipeline)
+            .map(|(solution, _)| solution)
+    }
+
+    pub(crate) fn solve_with_pipeline_counted(
+        &self,
+        pipeline: &[Technique],
+    ) -> Result<(Sudoku, PerfCounters), InvalidSudokuError> {
+        if self.check_valid().is_err() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        let mut counters = PerfCounters::default();
+        let mut solution = self
+            .solve_pipeline_rec(
+                &self.sound_pipeline(pipeline),
+                &mut counters,
+                &mut HashMap::new(),
+            )
+            .ok_or(InvalidSudokuError::Unsolvable)?;
+        // Eliminations are kept in the restrictions, which the solution doesn't need.
+        solution.restrictions = self.restrictions.clone();
+
+        Ok((solution, counters))
+    }
+
+    fn solve_pipeline_rec(
+        &self,
+        pipeline: &[Technique],
+        counters: &mut PerfCounters,
+        stats: &mut HashMap<Technique, usize>,
+    ) -> Option<Sudoku> {
+        counters.nodes += 1;
+
+        let mut sudoku = self.clone();
+        while pipeline.iter().any(|&technique| {
+            let progress = sudoku.apply_technique(technique);
+            if progress > 0 {
+                *stats.entry(technique).or_default() += progress;
+            }
+            progress > 0
+        }) {}
+
+        if sudoku.has_dead_end() {
+            return None;
+        }
+
+        counters.candidate_evaluations += sudoku.empty_cells();
+        let Some(coord) = sudoku.most_constrained_cell() else {
+            return Some(sudoku);
+        };
+
+        for n in Sudoku::mask_numbers(sudoku.possible_mask(coord)) {
+            let mut guess = sudoku.clone();
+            guess.set(coord, n);
+            counters.placements += 1;
+
+            if let Some(solution) = guess.solve_pipeline_rec(pipeline, counters, stats) {
+                return Some(solution);
+            }
+        }
+
+        counters.backtracks += 1;
+        None
+    }
+
+    /// Solves with every technique before each guess, counting what each one contributed: cells
+    /// filled for the singles and candidates ruled out for the rest. Work in branches that turned
+    /// out to be dead-ends counts too. Techniques that never did anything are left out, as is
+    /// `UniqueRectangles` for a puzzle without a unique solution.
+    pub fn strategy_stats(&self) -> HashMap<Technique, usize> {
+        let mut stats = HashMap::new();
+        if self.check_valid().is_ok() {
+            let pipeline = self.sound_pipeline(&Technique::ALL);
+            self.solve_pipeline_rec(&pipeline, &mut PerfCounters::default(), &mut stats);
+        }
+        stats
+    }
+
+    /// Solves with logic alone, using the techniques up to and including `max_technique`. Gives up
+    /// with `NeedsHarderTechnique` if they get stuck before the end, rather than guessing.
+    /// `UniqueRectangles` is left out unless the puzzle has a unique solution.
+    pub fn solve_logic_only(&self, max_technique: Technique) -> Result<Sudoku, InvalidSudokuError> {
+        if self.check_valid().is_err() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        let pipeline = Technique::ALL
+            .into_iter()
+            .filter(|&technique| technique <= max_technique)
+            .collect::<Vec<Technique>>();
+        let pipeline = self.sound_pipeline(&pipeline);
+        let mut sudoku = self.clone();
+        while pipeline
+            .iter()
+            .any(|&technique| sudoku.apply_technique(technique) > 0)
+        {}
+
+        if sudoku.has_dead_end() {
+            return Err(InvalidSudokuError::Unsolvable);
+        }
+        if !sudoku.is_complete() {
+            return Err(InvalidSudokuError::NeedsHarderTechnique);
+        }
+
+        sudoku.restrictions = self.restrictions.clone();
+        match sudoku.validate() {
+            Ok(_) => Ok(sudoku),
+            Err(_) => Err(InvalidSudokuError::Unsolvable),
+        }
+    }
+
+    /// Gets the hardest technique logic needs to solve the puzzle, the lowest cap that
+    /// `solve_logic_only` finishes with. Returns `None` if it can't be finished without guessing.
+    pub fn hardest_technique(&self) -> Option<Technique> {
+        Technique::ALL
+            .into_iter()
+            .find(|&technique| self.solve_logic_only(technique).is_ok())
+    }
+
+    /// Gets the techniques of the pipeline that are safe to run on this puzzle. `UniqueRectangles`
+    /// assumes a unique solution, so it's dropped for a puzzle without one. Guesses below a puzzle
+    /// with a unique solution have at most one, so it stays safe throughout the search.
+    fn sound_pipeline(&self, pipeline: &[Technique]) -> Vec<Technique> {
+        let unique = !pipeline.contains(&Technique::UniqueRectangles) || self.has_unique_solution();
+        pipeline
+            .iter()
+            .copied()
+            .filter(|&technique| unique || technique != Technique::UniqueRectangles)
+            .collect()
+    }
+
+    /// Rates the puzzle by the techniques it takes to solve: naked singles alone are easy, also
+    /// needing hidden singles is medium, and anything that needs a guess is hard.
+    pub fn difficulty(&self) -> Difficulty {
+        if self.is_complete() {
+            return Difficulty::Trivial;
+        }
+
+        let solves_with = |pipeline: &[Technique]| {
+            let mut sudoku = self.clone();
+            while pipeline
+                .iter()
+                .any(|&technique| sudoku.apply_technique(technique) > 0)
+            {}
+            sudoku.is_complete() && sudoku.validate().is_ok()
+        };
+
+        if solves_with(&[Technique::NakedSingles]) {
+            Difficulty::Easy
+        } else if solves_with(&[Technique::NakedSingles, Technique::HiddenSingles]) {
+            Difficulty::Medium
+        } else {
+            Difficulty::Hard
+        }
+    }
+
+    /// Applies a technique until it can't make any more progress, returning how much it made: cells
+    /// filled for the singles, and numbers ruled out for the rest.
+    pub(crate) fn apply_technique(&mut self, technique: Technique) -> usize {
+        match technique {
+            Technique::NakedSingles => self.fill_singles().len(),
+            Technique::HiddenSingles => {
+                let empty = self.empty_cells();
+                while self.apply_hidden_singles() {}
+                empty - self.empty_cells()
+            }
+            Technique::Swordfish => self.eliminate_repeatedly(Sudoku::eliminate_swordfish),
+            Technique::RemotePairs => self.eliminate_repeatedly(Sudoku::eliminate_remote_pairs),
+            Technique::UniqueRectangles => {
+                self.eliminate_repeatedly(Sudoku::eliminate_unique_rectangles)
+            }
+            Technique::XCycles => {
+                let side = self.side();
+                self.eliminate_repeatedly(|sudoku| {
+                    (1..=side).fold(false, |eliminated, digit| {
+                        sudoku.eliminate_x_cycles(digit) | eliminated
+                    })
+                })
+            }
+        }
+    }
+
+    /// Runs an elimination until it doesn't rule anything out, returning how many numbers it ruled
+    /// out in all.
+    fn eliminate_repeatedly(&mut self, mut eliminate: impl FnMut(&mut Sudoku) -> bool) -> usize {
+        let candidates = self.candidate_placements().len();
+        while eliminate(self) {}
+        candidates - self.candidate_placements().len()
+    }
+
+    /// Returns whether the puzzle can be solved by scanning alone, cross-hatching to find the only
+    /// spot for a number within a row, col, or house.
+    pub fn is_scanning_solvable(&self) -> bool {
+        let mut sudoku = self.clone();
+        sudoku.apply_technique(Technique::HiddenSingles);
+        sudoku.validate().is_ok()
+    }
+
+    /// Fills in the puzzle with logic, revealing the most constrained cell from the solution each time
+    /// logic gets stuck. Returns the revealed cells, along with the cells logic filled in a row
+    /// around each reveal (one more chain than reveals).
+    fn reveal_steps(&self, solution: &Sudoku) -> (Vec<Coord>, Vec<Vec<(Coord, u8)>>) {
+        let mut sudoku = self.clone();
+        let mut revealed = Vec::new();
+        let mut chains = vec![sudoku.fill_singles()];
+
+        while let Some(coord) = sudoku.most_constrained_cell() {
+            let Some(n) = solution.get(coord) else {
+                break;
+            };
+
+            sudoku.set(coord, n);
+            revealed.push(coord);
+            chains.push(sudoku.fill_singles());
+        }
+
+        (revealed, chains)
+    }
+
+    /// Returns the cells that need to be revealed from the solution so that the rest of the puzzle
+    /// can be finished with logic alone (no guessing). Cells are revealed greedily, picking the
+    /// most constrained cell each time logic gets stuck.
+    pub fn logic_unlock_cells(&self, solution: &Sudoku) -> Vec<Coord> {
+        self.reveal_steps(solution).0
+    }
+
+    /// Gets the longest run of cells filled by singles one after another, before logic gets stuck
+    /// and a cell has to be revealed to continue. Puzzles that collapse in one long chain feel easy.
+    pub fn max_single_chain(&self) -> usize {
+        let solution = self.solve().unwrap_or_else(|_| self.clone());
+        let (_, chains) = self.reveal_steps(&solution);
+        chains.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Returns the order the solver fills in cells, logic first, then a guess whenever logic gets
+    /// stuck. Replaying these placements onto the puzzle reconstructs the solution.
+    pub fn fill_order(&self) -> Result<Vec<(Coord, u8)>, InvalidSudokuError> {
+        let solution = self.solve()?;
+        let (revealed, chains) = self.reveal_steps(&solution);
+
+        let mut order = Vec::new();
+        let mut chains = chains.into_iter();
+        order.extend(chains.next().unwrap_or_default());
+        for (coord, chain) in revealed.into_iter().zip(chains) {
+            if let Some(n) = solution.get(coord) {
+                order.push((coord, n));
+            }
+            order.extend(chain);
+        }
+
+        Ok(order)
+    }
+
+    /// Lazily yields the placements that solve the puzzle, one each call to `next`, for driving an
+    /// animation at its own pace. Logic makes each placement it can, and once it gets stuck the
+    /// puzzle is solved (the first time only) and the most constrained cell is taken from the
+    /// solution, so backtracking never shows up and every placement is part of the solution. An
+    /// unsolvable puzzle stops once logic gets stuck.
+    pub fn solve_steps(&self) -> SolveIterator {
+        SolveIterator {
+            sudoku: self.clone(),
+            solution: None,
+        }
+    }
+
+    /// Scores how hard the puzzle is to solve by hand. It's worked through with the techniques,
+    /// going back to the easiest whenever one makes progress, and each cell filled or number ruled
+    /// out scores the technique's place in `Technique::ALL`, one for naked singles and up from there.
+    /// Whenever they all get stuck, the most constrained cell is revealed from the solution for
+    /// `GUESS_SCORE`. A solved grid scores zero. `UniqueRectangles` is skipped unless the puzzle has
+    /// a unique solution.
+    pub fn difficulty_score(&self) -> Result<usize, InvalidSudokuError> {
+        const GUESS_SCORE: usize = 20;
+
+        let solution = self.solve()?;
+        let pipeline = self.sound_pipeline(&Technique::ALL);
+        let mut sudoku = self.clone();
+        let mut score = 0;
+
+        loop {
+            let progress = Technique::ALL
+                .iter()
+                .enumerate()
+                .filter(|(_, technique)| pipeline.contains(technique))
+                .find_map(|(rank, &technique)| {
+                    let progress = sudoku.apply_technique(technique);
+                    (progress > 0).then_some((rank + 1) * progress)
+                });
+            if let Some(progress) = progress {
+                score += progress;
+                continue;
+            }
+
+            let Some(coord) = sudoku.most_constrained_cell() else {
+                return Ok(score);
+            };
+            let n = solution.get(coord).ok_or(InvalidSudokuError::Unsolvable)?;
+            sudoku.set(coord, n);
+            score += GUESS_SCORE;
+        }
+    }
+
+    /// Gets how hard the puzzle is for each of its clues, its `difficulty_score` over its clue
+    /// count. Higher means the puzzle gets more out of each given. An empty grid counts as one clue.
+    pub fn clue_efficiency(&self) -> Result<f32, InvalidSudokuError> {
+        let score = self.difficulty_score()?;
+        Ok(score as f32 / self.clue_count().max(1) as f32)
+    }
+
+    /// Gets how many placements the logic-first solve takes to finish the puzzle, a tiebreaker for
+    /// puzzles of the same difficulty where more steps feel longer.
+    pub fn solving_path_length(&self) -> Result<usize, InvalidSudokuError> {
+        Ok(self.fill_order()?.len())
+    }
+
+    /// Solves the sudoku, also returning a certificate of the placements that lead to the solution.
+    /// Each placement is the next `hint` along with its reason, or once logic gets stuck, the most
+    /// constrained cell taken from the solution as a guess.
+    pub fn solve_certified(&self) -> Result<(Self, Certificate), InvalidSudokuError> {
+        let solution = self.solve()?;
+        let mut sudoku = self.clone();
+        let mut steps = Vec::new();
+
+        loop {
+            let (coord, n, reason) = match sudoku.hint() {
+                Some((coord, n, reason)) => (coord, n, Some(reason)),
+                None => {
+                    let Some(coord) = sudoku.most_constrained_cell() else {
+                        break;
+                    };
+                    let n = solution.get(coord).ok_or(InvalidSudokuError::Unsolvable)?;
+                    (coord, n, None)
+                }
+            };
+            sudoku.set(coord, n);
+            steps.push((coord, n, reason));
+        }
+
+        Ok((sudoku, Certificate { steps }))
+    }
+
+    /// Replays a certificate onto the puzzle, checking each placement goes in an empty cell, is
+    /// possible at that point, and is forced the way its reason says: the only number left in the
+    /// cell for a naked single, or the only spot left for the number in one of the cell's units for
+    /// a hidden single. Guesses only need to be possible. Finally the result has to be a valid,
+    /// complete grid.
+    pub fn verify_certificate(puzzle: &Sudoku, cert: &Certificate) -> bool {
+        let mut sudoku = puzzle.clone();
+
+        for &(coord, n, reason) in &cert.steps {
+            if sudoku.get(coord).is_some() {
+                return false;
+            }
+
+            let mask = sudoku.possible_mask(coord);
+            if mask & (1 << n) == 0 {
+                return false;
+            }
+
+            let forced = match reason {
+                None => true,
+                Some(HintReason::NakedSingle) => mask.count_ones() == 1,
+                Some(HintReason::HiddenSingle) => sudoku
+                    .units()
+                    .into_iter()
+                    .filter(|unit| unit.contains(&coord))
+                    .any(|unit| {
+                        unit.iter().all(|&other| {
+                            other == coord
+                                || sudoku.get(other).is_some()
+                                || sudoku.possible_mask(other) & (1 << n) == 0
+                        })
+                    }),
+            };
+            if !forced {
+                return false;
+            }
+
+            sudoku.set(coord, n);
+        }
+
+        sudoku.validate().is_ok()
+    }
+
+    /// Gets how many cells logic can fill in after revealing the cell from the solution.
+    fn reveal_progress(&self, solution: &Sudoku, coord: Coord) -> usize {
+        let mut sudoku = self.clone();
+        if let Some(n) = solution.get(coord) {
+            sudoku.set(coord, n);
+        }
+        sudoku.fill_singles().len()
+    }
+
+    /// Gets the empty cell that, once revealed from the solution, lets logic fill in the most cells.
+    pub fn best_reveal_cell(&self, solution: &Sudoku) -> Option<Coord> {
+        let mut best: Option<(Coord, usize)> = None;
+
+        for coord in self.coords() {
+            if self.get(coord).is_some() {
+                continue;
+            }
+
+            let progress = self.reveal_progress(solution, coord);
+            if best.is_none_or(|(_, best_progress)| progress > best_progress) {
+                best = Some((coord, progress));
+            }
+        }
+
+        best.map(|(coord, _)| coord)
+    }
+
+    /// Gets how hard each cell was to fill: 0 for givens, 1 for cells filled by naked singles, 2 for
+    /// cells filled once hidden singles were needed, and `GUESSED_LEVEL` for cells revealed from the
+    /// solution when neither could make progress. Indexed by row then col.
+    pub fn difficulty_heatmap(&self) -> Vec<Vec<u8>> {
+        let solution = self.solve().ok();
+        let side = self.side() as usize;
+        let mut heatmap = vec![vec![0; side]; side];
+        let mut sudoku = self.clone();
+
+        loop {
+            let before = sudoku.clone();
+            let level = if !sudoku.fill_singles().is_empty() {
+                1
+            } else if sudoku.apply_technique(Technique::HiddenSingles) > 0 {
+                2
+            } else if let Some(coord) = sudoku.most_constrained_cell() {
+                let Some(n) = solution.as_ref().and_then(|solution| solution.get(coord)) else {
+                    break;
+                };
+                sudoku.set(coord, n);
+                Sudoku::GUESSED_LEVEL
+            } else {
+                break;
+            };
+
+            for coord in sudoku.coords() {
+                if before.get(coord).is_none() && sudoku.get(coord).is_some() {
+                    heatmap[coord.row as usize][coord.col as usize] = level;
+                }
+            }
+        }
+
+        heatmap
+    }
+
+    /// Gets the cell where logic first gets stuck, the most constrained cell once no more singles
+    /// can be filled. Returns None if logic alone fills the whole grid.
+    pub fn bottleneck_cell(&self) -> Option<Coord> {
+        let mut sudoku = self.clone();
+        sudoku.fill_singles();
+        sudoku.most_constrained_cell()
+    }
+
+    /// Gets the backdoors of the puzzle up to the given size: the smallest sets of empty cells that,
+    /// once filled from the solution, let singles finish the rest. Sets are in row-major order, and
+    /// a set is left out if a smaller backdoor is already inside it. A puzzle singles can already
+    /// finish has the empty set as its only backdoor, and an unsolvable one has none.
+    pub fn backdoors(&self, max_size: usize) -> Vec<Vec<Coord>> {
+        let Ok(solution) = self.solve() else {
+            return Vec::new();
+        };
+
+        let empty = self
+            .coords()
+            .filter(|&coord| self.get(coord).is_none())
+            .collect::<Vec<Coord>>();
+
+        let mut backdoors: Vec<Vec<Coord>> = Vec::new();
+        for size in 0..=max_size.min(empty.len()) {
+            // Walk every combination of `size` empty cells, as indices into `empty`.
+            let mut indices = (0..size).collect::<Vec<usize>>();
+            loop {
+                let cells = indices.iter().map(|&i| empty[i]).collect::<Vec<Coord>>();
+                let contains_smaller = backdoors
+                    .iter()
+                    .any(|backdoor| backdoor.iter().all(|coord| cells.contains(coord)));
+
+                if !contains_smaller {
+                    let mut sudoku = self.clone();
+                    for &coord in &cells {
+                        if let Some(n) = solution.get(coord) {
+                            sudoku.set(coord, n);
+                        }
+                    }
+                    sudoku.fill_singles();
+                    if sudoku.validate().is_ok() {
+                        backdoors.push(cells);
+                    }
+                }
+
+                // Advance to the next combination, stopping after the last one.
+                let Some(i) = (0..size)
+                    .rev()
+                    .find(|&i| indices[i] < empty.len() - size + i)
+                else {
+                    break;
+                };
+                indices[i] += 1;
+                for j in i + 1..size {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+        }
+
+        backdoors
+    }
+
+    /// Gets the remaining uncertainty of the grid in bits, the sum of log2 of the number of possible
+    /// numbers over the empty cells. A solved grid has zero entropy.
+    pub fn entropy(&self) -> f64 {
+        self.coords()
+            .filter(|&coord| self.get(coord).is_none())
+            .map(|coord| (self.possible_mask(coord).count_ones().max(1) as f64).log2())
+            .sum()
+    }
+
+    /// Gets every (cell, number) placement still possible, the variables of an exact cover or SAT
+    /// encoding. A filled cell only has its own number.
+    pub fn candidate_placements(&self) -> Vec<(Coord, u8)> {
+        let mut placements = Vec::new();
+
+        for coord in self.coords() {
+            match self.get(coord) {
+                Some(n) => placements.push((coord, n)),
+                None => placements.extend(
+                    self.get_sorted_possible_numbers(coord)
+                        .into_iter()
+                        .map(|n| (coord, n)),
+                ),
+            }
+        }
+
+        placements
+    }
+
+    /// Gets every almost locked set: N empty cells within a unit that between them have exactly N + 1
+    /// possible numbers. Cells are in row-major order, and a set shared by two units is only listed once.
+    pub fn almost_locked_sets(&self) -> Vec<(Vec<Coord>, HashSet<u8>)> {
+        let mut sets: Vec<(Vec<Coord>, HashSet<u8>)> = Vec::new();
+
+        for unit in self.units() {
+            let empty = unit
+                .into_iter()
+                .filter(|&coord| self.get(coord).is_none())
+                .map(|coord| (coord, self.get_possible_numbers(coord)))
+                .collect::<Vec<(Coord, HashSet<u8>)>>();
+
+            // Every non-empty subset of the unit's empty cells.
+            for mask in 1..(1u32 << empty.len()) {
+                let (mut cells, possible): (Vec<Coord>, Vec<&HashSet<u8>>) = empty
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| mask & (1 << i) != 0)
+                    .map(|(_, (coord, possible))| (*coord, possible))
+                    .unzip();
+                let numbers = possible
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .collect::<HashSet<u8>>();
+
+                if numbers.len() == cells.len() + 1 {
+                    cells.sort_by_key(|coord| (coord.row, coord.col));
+                    if !sets.iter().any(|(existing, _)| *existing == cells) {
+                        sets.push((cells, numbers));
+                    }
+                }
+            }
+        }
+
+        sets
+    }
+
+    /// Gets every hidden pair: two numbers whose only possible spots in a unit are the same two
+    /// cells, so any other number can be ruled out of them. Pairs where the two cells have nothing
+    /// else to rule out are left out, as there's nothing to learn from them. The grid isn't changed.
+    pub fn find_hidden_pairs(&self) -> Vec<HiddenPair> {
+        let mut pairs = Vec::new();
+
+        for (index, unit) in self.units().into_iter().enumerate() {
+            let spots = |n: u8| {
+                unit.iter()
+                    .copied()
+                    .filter(|&coord| {
+                        self.get(coord).is_none() && self.possible_mask(coord) & (1 << n) != 0
+                    })
+                    .collect::<Vec<Coord>>()
+            };
+
+            for a in 1..=self.side() {
+                let a_spots = spots(a);
+                let [first, second] = a_spots[..] else {
+                    continue;
+                };
+
+                for b in a + 1..=self.side() {
+                    let pair = 1 << a | 1 << b;
+                    let extra = [first, second]
+                        .iter()
+                        .any(|&coord| self.possible_mask(coord) & !pair != 0);
+                    if spots(b) == a_spots && extra {
+                        let unit = Unit::from_index(index, self.box_size);
+                        pairs.push((unit, (first, second), (a, b)));
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+
+    /// Gets the eliminations from box-line intersections, without applying them. When a number's
+    /// possible spots in a house all share a row or col (pointing), it can't go anywhere else in that
+    /// row or col. When its spots in a row or col all share a house (claiming), it can't go anywhere
+    /// else in that house. Each elimination comes with a reason.
+    pub fn intersection_removals(&self) -> Vec<(Coord, u8, String)> {
+        let mut removals: Vec<(Coord, u8, String)> = Vec::new();
+        let units = self.units();
+        let side = self.side();
+        let (lines, houses) = units.split_at(2 * side as usize);
+
+        let spots = |unit: &[Coord], n: u8| {
+            unit.iter()
+                .copied()
+                .filter(|&coord| self.get_possible_numbers(coord).contains(&n))
+                .collect::<Vec<Coord>>()
+        };
+
+        let mut remove = |from: &[Coord], keep: &[Coord], n: u8, reason: String| {
+            for &coord in from {
+                let possible = self.get_possible_numbers(coord).contains(&n);
+                let seen = removals.iter().any(|(c, m, _)| *c == coord && *m == n);
+                if possible && !keep.contains(&coord) && !seen {
+                    removals.push((coord, n, reason.clone()));
+                }
+            }
+        };
+
+        for n in 1..=side {
+            // Pointing
+            for (house_index, house) in houses.iter().enumerate() {
+                let house_spots = spots(house, n);
+                for (line_index, line) in lines.iter().enumerate() {
+                    if house_spots.len() > 1 && house_spots.iter().all(|coord| line.contains(coord))
+                    {
+                        let reason = format!(
+                            "{n} in house {house_index} can only go in {}",
+                            Sudoku::line_name(line_index, side)
+                        );
+                        remove(line, house, n, reason);
+                    }
+                }
+            }
+
+            // Claiming
+            for (line_index, line) in lines.iter().enumerate() {
+                let line_spots = spots(line, n);
+                for (house_index, house) in houses.iter().enumerate() {
+                    if line_spots.len() > 1 && line_spots.iter().all(|coord| house.contains(coord))
+                    {
+                        let reason = format!(
+                            "{n} in {} can only go in house {house_index}",
+                            Sudoku::line_name(line_index, side)
+                        );
+                        remove(house, line, n, reason);
+                    }
+                }
+            }
+        }
+
+        removals
+    }
+
+    /// Names a row or col by its index in `units` for a grid with the given side length.
+    fn line_name(index: usize, side: u8) -> String {
+        let side = side as usize;
+        if index < side {
+            format!("row {index}")
+        } else {
+            format!("col {}", index - side)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Sudoku {
+        /// Checks a technique applied to `before` (giving this grid) didn't rule out the answer
+        /// anywhere: every filled cell matches the unique solution, and every empty cell still has
+        /// the solution's number as a possibility.
+        pub(crate) fn assert_elimination_sound(&self, before: &Sudoku) {
+            let solution = before.solve().unwrap();
+            assert_eq!(1, before.count_solutions(2));
+
+            for row in 0..9 {
+                for col in 0..9 {
+                    let coord = Coord { row, col };
+                    let n = solution.get(coord).unwrap();
+                    match self.get(coord) {
+                        Some(filled) => assert_eq!(n, filled, "wrong number at {coord:?}"),
+                        None => assert!(
+                            self.get_possible_numbers(coord).contains(&n),
+                            "eliminated {n}, the answer at {coord:?}"
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_assert_elimination_sound() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+
+        let mut singles = sudoku.clone();
+        singles.fill_singles();
+        singles.assert_elimination_sound(&sudoku);
+
+        // Ruling out the answer for a cell trips it.
+        let solution = sudoku.solve().unwrap();
+        let coord = Coord { row: 0, col: 0 };
+        let answer = solution.get(coord).unwrap();
+        let mut unsound = sudoku.clone();
+        unsound.restrict(coord, (1..=9).filter(|&m| m != answer));
+        let result = std::panic::catch_unwind(|| unsound.assert_elimination_sound(&sudoku));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_logic_unlock_cells() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+
+        // This one can't be finished with logic alone.
+        let mut logic_only = sudoku.clone();
+        logic_only.fill_singles();
+        assert!(logic_only.validate().is_err());
+
+        let revealed = sudoku.logic_unlock_cells(&solution);
+        assert!(!revealed.is_empty());
+
+        let mut unlocked = sudoku.clone();
+        for coord in revealed {
+            unlocked.set(coord, solution.get(coord).unwrap());
+        }
+        unlocked.fill_singles();
+        assert_eq!(solution, unlocked);
+    }
+
+    #[test]
+    fn test_propagate() {
+        let sudoku = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let mut propagated = sudoku.clone();
+        assert_eq!(Ok(true), propagated.propagate());
+        assert_eq!(expected, propagated);
+        assert_eq!(Ok(false), propagated.propagate());
+
+        // Nothing left for the search to guess.
+        let (solution, counters) = sudoku.solve_counted().unwrap();
+        assert_eq!(expected, solution);
+        assert_eq!(0, counters.placements);
+
+        // The only number left for the top-left cell is ruled out by its restriction.
+        let mut dead_end = expected.clone();
+        dead_end.unset(Coord { row: 0, col: 0 });
+        dead_end.restrict(Coord { row: 0, col: 0 }, (1..=7).chain(9..=9));
+        assert_eq!(Err(InvalidSudokuError::Unsolvable), dead_end.propagate());
+    }
+
+    #[test]
+    fn test_apply_hidden_singles() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        // Naked singles alone get stuck on this one.
+        let mut singles = sudoku.clone();
+        singles.fill_singles();
+        assert!(!singles.is_complete());
+
+        let mut hidden = singles.clone();
+        assert!(hidden.apply_hidden_singles());
+        assert!(hidden.clue_count() > singles.clue_count());
+        assert!(hidden.mistakes(&expected).is_empty());
+
+        let (solution, counters) = sudoku.solve_counted().unwrap();
+        assert_eq!(expected, solution);
+        assert_eq!(0, counters.placements);
+        assert!(!solution.clone().apply_hidden_singles());
+    }
+
+    #[test]
+    fn test_hint() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solution = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let (coord, n, _) = sudoku.hint().unwrap();
+        assert!(sudoku.get(coord).is_none());
+        assert_eq!(Some(n), solution.get(coord));
+
+        // Once naked singles run out, hidden singles take over.
+        let mut singles = sudoku.clone();
+        singles.fill_singles();
+        let (coord, n, reason) = singles.hint().unwrap();
+        assert_eq!(HintReason::HiddenSingle, reason);
+        assert_eq!(Some(n), solution.get(coord));
+
+        // After propagating, only a guess can make progress.
+        let mut stuck = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        stuck.propagate().unwrap();
+        assert!(!stuck.is_complete());
+        assert_eq!(None, stuck.hint());
+    }
+
+    #[test]
+    fn test_hint_sequence() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solution = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let hints = sudoku.hint_sequence(3);
+        assert_eq!(3, hints.len());
+        assert_eq!(sudoku.hint(), Some(hints[0]));
+
+        // Each hint is the next one once those before it are placed, and none of them clash.
+        let mut replay = sudoku.clone();
+        for &(coord, n, reason) in &hints {
+            assert_eq!(Some((coord, n, reason)), replay.hint());
+            assert_eq!(Some(n), solution.get(coord));
+            replay.set(coord, n);
+        }
+
+        // It stops where logic does, here at the end of the puzzle.
+        let hints = sudoku.hint_sequence(Sudoku::CELL_COUNT);
+        assert_eq!(sudoku.empty_cells(), hints.len());
+
+        let mut stuck = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        stuck.propagate().unwrap();
+        assert!(stuck.hint_sequence(3).is_empty());
+    }
+
+    #[test]
+    fn test_solve_with_pipeline() {
+        let sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = sudoku.solve().unwrap();
+
+        let (singles, singles_counters) = sudoku
+            .solve_with_pipeline_counted(&[Technique::NakedSingles, Technique::HiddenSingles])
+            .unwrap();
+        let (backtracking, backtracking_counters) =
+            sudoku.solve_with_pipeline_counted(&[]).unwrap();
+
+        assert_eq!(expected, singles);
+        assert_eq!(expected, backtracking);
+        assert!(singles_counters.nodes < backtracking_counters.nodes);
+        assert_eq!(
+            expected,
+            sudoku
+                .solve_with_pipeline(&[Technique::HiddenSingles])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_pipeline_without_unique_solution() {
+        // Four solutions, and unique rectangles rule out real ones, which used to leave the pipeline
+        // with none at all.
+        let sudoku =
+            "7..2.6..9.2..9.....961..4..9.7.14.3.6....7.....2968....6......8.7.....61.1..3..25"
+                .parse::<Sudoku>()
+                .unwrap();
+        assert_eq!(4, sudoku.count_solutions(10));
+        let mut eliminated = sudoku.clone();
+        eliminated.propagate().unwrap();
+        while eliminated.eliminate_unique_rectangles() {}
+        assert!(eliminated.count_solutions(10) < 4);
+
+        let pipeline = [
+            Technique::NakedSingles,
+            Technique::HiddenSingles,
+            Technique::UniqueRectangles,
+        ];
+        let solution = sudoku.solve_with_pipeline(&pipeline).unwrap();
+        assert!(solution.validate().is_ok());
+        assert!(sudoku.solution_preserves_givens(&solution));
+        assert!(!sudoku
+            .strategy_stats()
+            .contains_key(&Technique::UniqueRectangles));
+        assert!(sudoku.difficulty_score().is_ok());
+        assert_eq!(
+            Err(InvalidSudokuError::NeedsHarderTechnique),
+            sudoku.solve_logic_only(Technique::XCycles)
+        );
+    }
+
+    #[test]
+    fn test_solve_logic_only() {
+        // Singles get stuck on this one, and it takes a swordfish to go on.
+        let sudoku = include_str!("swordfish.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(
+            Err(InvalidSudokuError::NeedsHarderTechnique),
+            sudoku.solve_logic_only(Technique::HiddenSingles)
+        );
+        assert_eq!(
+            sudoku.solve(),
+            sudoku.solve_logic_only(Technique::Swordfish)
+        );
+        assert_eq!(sudoku.solve(), sudoku.solve_logic_only(Technique::XCycles));
+        assert_eq!(Some(Technique::Swordfish), sudoku.hardest_technique());
+
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(
+            singles.solve(),
+            singles.solve_logic_only(Technique::NakedSingles)
+        );
+        assert_eq!(Some(Technique::NakedSingles), singles.hardest_technique());
+
+        let mut repeated = singles.clone();
+        repeated.set(Coord { row: 0, col: 2 }, 9);
+        assert_eq!(
+            Err(InvalidSudokuError::DuplicateGiven),
+            repeated.solve_logic_only(Technique::XCycles)
+        );
+    }
+
+    #[test]
+    fn test_strategy_stats() {
+        // Singles alone finish these, so nothing else gets a chance and every cell is one placement.
+        let singles = [Technique::NakedSingles, Technique::HiddenSingles];
+        for puzzle in [include_str!("singles.txt"), include_str!("easy.txt")] {
+            let sudoku = puzzle.parse::<Sudoku>().unwrap();
+            let stats = sudoku.strategy_stats();
+            assert!(stats.keys().all(|technique| singles.contains(technique)));
+            assert_eq!(sudoku.empty_cells(), stats.values().sum::<usize>());
+        }
+
+        let sudoku = include_str!("swordfish.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.strategy_stats()[&Technique::Swordfish] > 0);
+
+        let mut repeated = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        repeated.set(Coord { row: 0, col: 2 }, 9);
+        assert!(repeated.strategy_stats().is_empty());
+    }
+
+    #[test]
+    fn test_difficulty() {
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(Difficulty::Easy, singles.difficulty());
+        assert_eq!(Difficulty::Trivial, singles.solve().unwrap().difficulty());
+
+        // Despite the name, this one needs a hidden single along the way.
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(Difficulty::Medium, easy.difficulty());
+
+        let escargot = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(Difficulty::Hard, escargot.difficulty());
+    }
+
+    #[test]
+    fn test_enforce_arc_consistency() {
+        let candidates = |sudoku: &Sudoku| {
+            (0..9)
+                .flat_map(|row| (0..9).map(move |col| Coord { row, col }))
+                .filter(|&coord| sudoku.get(coord).is_none())
+                .map(|coord| sudoku.get_possible_numbers(coord).len())
+                .sum::<usize>()
+        };
+
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+
+        // A single pass only places the singles already there at the start.
+        let mut single_pass = sudoku.clone();
+        let singles = (0..9)
+            .flat_map(|row| (0..9).map(move |col| Coord { row, col }))
+            .filter(|&coord| sudoku.get(coord).is_none())
+            .filter_map(
+                |coord| match sudoku.get_sorted_possible_numbers(coord)[..] {
+                    [n] => Some((coord, n)),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<(Coord, u8)>>();
+        for (coord, n) in singles {
+            single_pass.set(coord, n);
+        }
+
+        let mut consistent = sudoku.clone();
+        assert!(consistent.enforce_arc_consistency());
+        assert!(candidates(&consistent) < candidates(&single_pass));
+        consistent.assert_elimination_sound(&sudoku);
+
+        // Arc consistency only sees naked singles, so the hidden single this one needs is left.
+        let mut propagated = sudoku.clone();
+        propagated.propagate().unwrap();
+        assert!(!consistent.is_complete());
+        assert!(propagated.is_complete());
+
+        // A second 9 in the top row.
+        let mut contradiction = sudoku.clone();
+        contradiction.set(Coord { row: 0, col: 0 }, 9);
+        assert!(!contradiction.enforce_arc_consistency());
+    }
+
+    #[test]
+    fn test_max_single_chain() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let hard = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        assert!(easy.max_single_chain() > hard.max_single_chain());
+    }
+
+    #[test]
+    fn test_fill_order() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+
+        let mut replay = sudoku.clone();
+        for (coord, n) in sudoku.fill_order().unwrap() {
+            assert_eq!(None, replay.get(coord));
+            replay.set(coord, n);
+        }
+        assert_eq!(solution, replay);
+    }
+
+    #[test]
+    fn test_solve_steps() {
+        let sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+
+        let mut replay = sudoku.clone();
+        for (coord, n) in sudoku.solve_steps() {
+            assert_eq!(None, replay.get(coord));
+            replay.set(coord, n);
+        }
+        assert_eq!(solution, replay);
+
+        assert_eq!(None, solution.solve_steps().next());
+
+        // A second 9 in the top row.
+        let mut unsolvable = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert!(unsolvable.solve_steps().count() < unsolvable.empty_cells());
+    }
+
+    #[test]
+    fn test_difficulty_score() {
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let escargot = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+
+        // Naked singles score one a cell.
+        assert_eq!(singles.empty_cells(), singles.difficulty_score().unwrap());
+        assert!(easy.difficulty_score().unwrap() > easy.empty_cells());
+        assert!(escargot.difficulty_score().unwrap() > easy.difficulty_score().unwrap());
+        assert_eq!(0, easy.solve().unwrap().difficulty_score().unwrap());
+
+        let mut unsolvable = easy.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert!(unsolvable.difficulty_score().is_err());
+    }
+
+    #[test]
+    fn test_clue_efficiency() {
+        // Sparse and hard against dense and easy.
+        let seventeen = include_str!("seventeen.txt").parse::<Sudoku>().unwrap();
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert!(seventeen.clue_count() < singles.clue_count());
+        assert!(seventeen.clue_efficiency().unwrap() > singles.clue_efficiency().unwrap());
+
+        let singles_score = singles.difficulty_score().unwrap() as f32;
+        assert_eq!(
+            singles_score / singles.clue_count() as f32,
+            singles.clue_efficiency().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_solving_path_length() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(45, sudoku.solving_path_length().unwrap());
+
+        // Same puzzle, less one redundant clue.
+        let mut sparser = sudoku.clone();
+        sparser.unset(Coord { row: 0, col: 8 });
+        assert!(sparser.solving_path_length().unwrap() > sudoku.solving_path_length().unwrap());
+    }
+
+    #[test]
+    fn test_solve_certified() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let (solution, cert) = sudoku.solve_certified().unwrap();
+        assert_eq!(sudoku.solve().unwrap(), solution);
+        assert!(Sudoku::verify_certificate(&sudoku, &cert));
+
+        let mut tampered = cert.clone();
+        let (coord, n, reason) = tampered.steps[0];
+        tampered.steps[0] = (coord, n % 9 + 1, reason);
+        assert!(!Sudoku::verify_certificate(&sudoku, &tampered));
+
+        let mut incomplete = cert.clone();
+        incomplete.steps.pop();
+        assert!(!Sudoku::verify_certificate(&sudoku, &incomplete));
+
+        // Every step is forced by its reason, up until logic gets stuck and has to guess.
+        let hard = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let (_, cert) = hard.solve_certified().unwrap();
+        assert!(Sudoku::verify_certificate(&hard, &cert));
+        let guess = cert
+            .steps
+            .iter()
+            .position(|&(_, _, reason)| reason.is_none())
+            .unwrap();
+
+        // Passing the guess off as a naked or hidden single is caught, though it's the right number.
+        for reason in [HintReason::NakedSingle, HintReason::HiddenSingle] {
+            let mut disguised = cert.clone();
+            disguised.steps[guess].2 = Some(reason);
+            assert!(!Sudoku::verify_certificate(&hard, &disguised));
+        }
+
+        // So is a step moved ahead of the logic that forces it.
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let (_, cert) = easy.solve_certified().unwrap();
+        assert!(cert.steps.iter().all(|&(_, _, reason)| reason.is_some()));
+        let mut skipped = cert.clone();
+        let last = skipped.steps.pop().unwrap();
+        skipped.steps.insert(0, last);
+        assert!(!Sudoku::verify_certificate(&easy, &skipped));
+    }
+
+    #[test]
+    fn test_best_reveal_cell() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+
+        let best = sudoku.best_reveal_cell(&solution).unwrap();
+        assert_eq!(None, sudoku.get(best));
+
+        let best_progress = sudoku.reveal_progress(&solution, best);
+        for coord in [Coord { row: 0, col: 0 }, Coord { row: 8, col: 8 }] {
+            assert!(best_progress >= sudoku.reveal_progress(&solution, coord));
+        }
+    }
+
+    #[test]
+    fn test_difficulty_heatmap() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let heatmap = easy.difficulty_heatmap();
+        for row in 0..9 {
+            for col in 0..9 {
+                let level = heatmap[row as usize][col as usize];
+                assert_eq!(easy.get(Coord { row, col }).is_some(), level == 0);
+            }
+        }
+
+        // Scanning finishes the easy one, so nothing is guessed, but some of it needs hidden singles.
+        assert!(heatmap
+            .iter()
+            .flatten()
+            .all(|&level| level < Sudoku::GUESSED_LEVEL));
+        assert!(heatmap.iter().flatten().any(|&level| level == 2));
+
+        let hard = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let heatmap = hard.difficulty_heatmap();
+        assert!(heatmap
+            .iter()
+            .flatten()
+            .any(|&level| level == Sudoku::GUESSED_LEVEL));
+        for row in 0..9 {
+            for col in 0..9 {
+                if hard.get(Coord { row, col }).is_some() {
+                    assert_eq!(0, heatmap[row as usize][col as usize]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bottleneck_cell() {
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(None, singles.bottleneck_cell());
+
+        let hard = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let coord = hard.bottleneck_cell().unwrap();
+        assert_eq!(None, hard.get(coord));
+        assert!(hard.get_possible_numbers(coord).len() > 1);
+    }
+
+    #[test]
+    fn test_backdoors() {
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(vec![Vec::<Coord>::new()], singles.backdoors(1));
+
+        // Singles alone get stuck on this one, but one revealed cell is enough.
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+        let backdoors = sudoku.backdoors(1);
+        assert!(!backdoors.is_empty());
+
+        for backdoor in backdoors {
+            assert_eq!(1, backdoor.len());
+            let mut unlocked = sudoku.clone();
+            unlocked.set(backdoor[0], solution.get(backdoor[0]).unwrap());
+            unlocked.fill_singles();
+            assert_eq!(solution, unlocked);
+        }
+    }
+
+    #[test]
+    fn test_is_scanning_solvable() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(easy.is_scanning_solvable());
+
+        let hard = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        assert!(!hard.is_scanning_solvable());
+
+        let four = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+        assert!(four.is_scanning_solvable());
+        assert_eq!(
+            four.solve().unwrap(),
+            four.solve_with_pipeline(&[Technique::HiddenSingles])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_entropy() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let before = sudoku.entropy();
+        sudoku.fill_singles();
+        assert!(sudoku.entropy() < before);
+
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(0.0, solved.entropy());
+    }
+
+    #[test]
+    fn test_candidate_placements() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let placements = sudoku.candidate_placements();
+
+        let candidates = (0..9)
+            .flat_map(|row| (0..9).map(move |col| Coord { row, col }))
+            .filter(|&coord| sudoku.get(coord).is_none())
+            .map(|coord| sudoku.get_possible_numbers(coord).len())
+            .sum::<usize>();
+        assert_eq!(candidates + sudoku.clue_count(), placements.len());
+
+        assert!(placements.contains(&(Coord { row: 0, col: 1 }, 9)));
+        assert!(placements.contains(&(Coord { row: 0, col: 0 }, 4)));
+        assert!(!placements.contains(&(Coord { row: 0, col: 0 }, 9)));
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        let mut sixteen = Sudoku::base_pattern(4);
+        for coord in sixteen.coords().collect::<Vec<Coord>>() {
+            if (coord.row + coord.col) % 3 == 0 {
+                sixteen.unset(coord);
+            }
+        }
+        let four = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+
+        for sudoku in [four, sixteen] {
+            let side = sudoku.side() as usize;
+            let solution = sudoku.solve().unwrap();
+
+            assert!(sudoku.entropy() > 0.0);
+            assert_eq!(0.0, solution.entropy());
+
+            let candidates = sudoku
+                .coords()
+                .map(|coord| sudoku.get_possible_numbers(coord).len())
+                .sum::<usize>();
+            assert_eq!(
+                candidates + sudoku.clue_count(),
+                sudoku.candidate_placements().len()
+            );
+
+            let best = sudoku.best_reveal_cell(&solution).unwrap();
+            assert_eq!(None, sudoku.get(best));
+
+            for backdoor in sudoku.backdoors(1) {
+                let mut unlocked = sudoku.clone();
+                for coord in backdoor {
+                    unlocked.set(coord, solution.get(coord).unwrap());
+                }
+                unlocked.fill_singles();
+                assert!(unlocked.validate().is_ok());
+            }
+
+            let heatmap = sudoku.difficulty_heatmap();
+            assert_eq!(side, heatmap.len());
+            for coord in sudoku.coords() {
+                let level = heatmap[coord.row as usize][coord.col as usize];
+                assert_eq!(sudoku.get(coord).is_some(), level == 0);
+            }
+
+            for (coord, n, reason) in sudoku.intersection_removals() {
+                assert!(sudoku.get_possible_numbers(coord).contains(&n));
+                assert!(!reason.contains(&format!("row {side}")));
+                assert!(!reason.contains(&format!("col {side}")));
+            }
+        }
+    }
+
+    #[test]
+    fn test_almost_locked_sets() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let sets = sudoku.almost_locked_sets();
+
+        let expected: HashSet<u8> = vec![3, 4].into_iter().collect();
+        assert!(sets.contains(&(vec![Coord { row: 8, col: 0 }], expected)));
+
+        for (cells, numbers) in sets {
+            assert_eq!(cells.len() + 1, numbers.len());
+            let same_unit = sudoku
+                .units()
+                .iter()
+                .any(|unit| cells.iter().all(|coord| unit.contains(coord)));
+            assert!(same_unit);
+        }
+    }
+
+    #[test]
+    fn test_find_hidden_pairs() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let pairs = sudoku.find_hidden_pairs();
+
+        // The bottom row only has room for its 3 and 4 in its first and third cells.
+        let cells = (Coord { row: 8, col: 0 }, Coord { row: 8, col: 2 });
+        assert!(pairs.contains(&(Unit::Row(8), cells, (3, 4))));
+        assert_eq!(
+            HashSet::from([3, 4, 5]),
+            sudoku.get_possible_numbers(cells.1)
+        );
+
+        let solution = sudoku.solve().unwrap();
+        for (_, (a, b), (n, m)) in pairs {
+            let answers = HashSet::from([solution.get(a).unwrap(), solution.get(b).unwrap()]);
+            assert_eq!(HashSet::from([n, m]), answers);
+        }
+
+        assert!(solution.find_hidden_pairs().is_empty());
+    }
+
+    #[test]
+    fn test_intersection_removals() {
+        let sudoku = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        let solution = sudoku.solve().unwrap();
+        let removals = sudoku.intersection_removals();
+
+        // The 3s in the middle-right house are all in col 7, so 3 can't go higher up col 7.
+        let pointing = (
+            Coord { row: 1, col: 7 },
+            3,
+            String::from("3 in house 5 can only go in col 7"),
+        );
+        assert!(removals.contains(&pointing));
+
+        let mut eliminated = sudoku.clone();
+        for (coord, n, _) in removals {
+            assert_ne!(Some(n), solution.get(coord));
+            assert!(sudoku.get_possible_numbers(coord).contains(&n));
+            eliminated.restrict(coord, (1..=9).filter(|&m| m != n));
+        }
+        eliminated.assert_elimination_sound(&sudoku);
+    }
+}
@@ -0,0 +1,1295 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Write},
+};
+
+use crate::{rng::Rng, BatchError, Coord, Difficulty, InvalidSudokuError, Sudoku, Technique};
+
+/// Solve status of a single puzzle in a batch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PuzzleStatus {
+    pub solvable: bool,
+    pub unique: bool,
+    pub clue_count: usize,
+}
+
+/// Numbers summing up a whole batch of puzzles, from `batch_stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchStats {
+    /// Mean clue count.
+    pub average_clues: f32,
+    /// How many of the puzzles rate at each difficulty.
+    pub difficulty_histogram: HashMap<Difficulty, usize>,
+    /// Share of the puzzles, out of 100, that can't be finished without guessing.
+    pub percent_guessing: f32,
+    /// Mean of `solve_counted`'s nodes, over the puzzles that have a solution.
+    pub average_nodes: f32,
+}
+
+impl Sudoku {
+    /// Most solutions `solve_with_confidence` counts, so the least confidence it gives is one over it.
+    pub const CONFIDENCE_SOLUTION_LIMIT: usize = 10;
+
+    /// Counts the solutions of the puzzle, stopping once `limit` is reached. Pass 2 to cheaply check
+    /// whether the solution is unique. Only completions that also satisfy any attached diagonals,
+    /// cages, and adjacency rules count.
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        // Duplicates can never be completed, don't bother searching.
+        if self.has_duplicates() {
+            return 0;
+        }
+
+        self.clone().count_rec(limit)
+    }
+
+    fn count_rec(&mut self, limit: usize) -> usize {
+        // Always branch on the most constrained cell, it keeps the search tree small.
+        let Some(coord) = self.most_constrained_cell() else {
+            // Full grid, which is only a solution if it's valid.
+            return usize::from(self.validate().is_ok());
+        };
+
+        let mut count = 0;
+        for n in Sudoku::mask_numbers(self.possible_mask(coord)) {
+            if count >= limit {
+                break;
+            }
+
+            self.set(coord, n);
+            count += self.count_rec(limit - count);
+        }
+
+        self.unset(coord);
+        count
+    }
+
+    /// Lazily yields every solution of the puzzle, searching only as far as needed for the next one.
+    /// Solutions come out in the same order the search finds them, smallest numbers first.
+    pub fn solutions(&self) -> impl Iterator<Item = Sudoku> {
+        // Duplicates can never be completed, start with nothing to search.
+        let mut stack = if self.has_duplicates() {
+            Vec::new()
+        } else {
+            vec![self.clone()]
+        };
+
+        std::iter::from_fn(move || {
+            while let Some(sudoku) = stack.pop() {
+                let Some(coord) = sudoku.most_constrained_cell() else {
+                    // Full grid, which is only a solution if it's valid.
+                    if sudoku.validate().is_ok() {
+                        return Some(sudoku);
+                    }
+                    continue;
+                };
+
+                // Push in reverse so the smallest number is searched first.
+                for n in sudoku.get_sorted_possible_numbers(coord).into_iter().rev() {
+                    let mut next = sudoku.clone();
+                    next.set(coord, n);
+                    stack.push(next);
+                }
+            }
+
+            None
+        })
+    }
+
+    /// Counts every solution that has both the puzzle's givens and the extra fixed cells. A fixed cell
+    /// that disagrees with a given has no solutions.
+    pub fn count_completions_with_fixed(&self, fixed: &[(Coord, u8)]) -> usize {
+        let mut sudoku = self.clone();
+
+        for &(coord, n) in fixed {
+            if sudoku.get(coord).is_some_and(|given| given != n) {
+                return 0;
+            }
+            sudoku.set(coord, n);
+        }
+
+        sudoku.count_solutions(usize::MAX)
+    }
+
+    /// Solves the sudoku, also returning how confident it is the solution is the intended one: 1.0
+    /// for a unique solution, otherwise one over the number of solutions, counting up to
+    /// `CONFIDENCE_SOLUTION_LIMIT`.
+    pub fn solve_with_confidence(&self) -> Result<(Self, f32), InvalidSudokuError> {
+        let solution = self.solve()?;
+        let solutions = self
+            .count_solutions(Sudoku::CONFIDENCE_SOLUTION_LIMIT)
+            .max(1);
+
+        Ok((solution, 1.0 / solutions as f32))
+    }
+
+    /// Returns whether the puzzle has exactly one solution. For a full grid that's a check it has no
+    /// repeated numbers, for a generated solution with cells taken out it's a check the rest still
+    /// pins it down. Attached variant rules count too, so a puzzle can be unique as a variant but
+    /// not as a standard puzzle, or the other way around.
+    pub fn has_unique_solution(&self) -> bool {
+        self.count_solutions(2) == 1
+    }
+
+    /// Returns whether the grid's filled cells, taken as givens, have exactly one completion. The same
+    /// check as `has_unique_solution`, read as a check on a completed or partly emptied grid.
+    pub fn is_sole_completion_of_own_givens(&self) -> bool {
+        self.has_unique_solution()
+    }
+
+    /// Returns whether the puzzle is proper, with a unique solution and no clue that could be removed
+    /// without losing it.
+    pub fn is_proper(&self) -> bool {
+        self.has_unique_solution() && !self.coords().any(|coord| self.is_clue_redundant(coord))
+    }
+
+    /// Returns whether the clue at the coord can be removed with the puzzle still having a unique
+    /// solution. Empty cells aren't clues, so they're never redundant.
+    pub fn is_clue_redundant(&self, coord: Coord) -> bool {
+        if self.get(coord).is_none() {
+            return false;
+        }
+
+        let mut sudoku = self.clone();
+        sudoku.unset(coord);
+        sudoku.count_solutions(2) == 1
+    }
+
+    /// Gets the minimal unavoidable sets of the solution with up to `max_size` cells: sets of cells
+    /// whose numbers can be rearranged into another valid grid, so a puzzle with a unique solution
+    /// needs a clue in every one of them. They're found from the other grids that only move two or
+    /// three numbers around, which covers every set of up to 7 cells. Sets are in row-major order,
+    /// smallest first, and an unsolvable puzzle has none.
+    pub fn unavoidable_sets(&self, max_size: usize) -> Vec<Vec<Coord>> {
+        let Ok(solution) = self.solve() else {
+            return Vec::new();
+        };
+
+        let mut sets: Vec<Vec<Coord>> = Vec::new();
+        for numbers in 1..1u32 << solution.side() {
+            if !(2..=3).contains(&numbers.count_ones()) {
+                continue;
+            }
+
+            let mut sudoku = solution.clone();
+            for coord in solution.coords() {
+                if solution
+                    .get(coord)
+                    .is_some_and(|n| numbers & (1 << (n - 1)) != 0)
+                {
+                    sudoku.unset(coord);
+                }
+            }
+
+            sudoku.moved_cells_rec(&solution, max_size, &mut Vec::new(), &mut sets);
+        }
+
+        // Only keep the minimal ones, with no smaller set inside them.
+        sets.sort_by_key(Vec::len);
+        let mut minimal: Vec<Vec<Coord>> = Vec::new();
+        for set in sets {
+            if !minimal
+                .iter()
+                .any(|smaller| smaller.iter().all(|coord| set.contains(coord)))
+            {
+                minimal.push(set);
+            }
+        }
+
+        minimal
+    }
+
+    /// Fills in the empty cells every possible way, collecting the cells moved away from `solution`
+    /// in each full grid reached. A branch is cut off as soon as it moves more than `max_size`
+    /// cells, rather than enumerating every grid and filtering afterwards.
+    fn moved_cells_rec(
+        &mut self,
+        solution: &Sudoku,
+        max_size: usize,
+        moved: &mut Vec<Coord>,
+        sets: &mut Vec<Vec<Coord>>,
+    ) {
+        let Some(coord) = self.most_constrained_cell() else {
+            let mut set = moved.clone();
+            set.sort_by_key(|coord| (coord.row, coord.col));
+            if !set.is_empty() && !sets.contains(&set) {
+                sets.push(set);
+            }
+            return;
+        };
+
+        for n in Sudoku::mask_numbers(self.possible_mask(coord)) {
+            let moves = solution.get(coord) != Some(n);
+            if moves && moved.len() == max_size {
+                continue;
+            }
+
+            self.set(coord, n);
+            if moves {
+                moved.push(coord);
+            }
+            self.moved_cells_rec(solution, max_size, moved, sets);
+            if moves {
+                moved.pop();
+            }
+        }
+        self.unset(coord);
+    }
+
+    /// Solves the sudoku, also returning each given that could be removed on its own without losing
+    /// the unique solution.
+    pub fn solve_with_redundancy(&self) -> Result<(Self, Vec<Coord>), InvalidSudokuError> {
+        let solution = self.solve()?;
+
+        let redundant = self
+            .coords()
+            .filter(|&coord| self.is_clue_redundant(coord))
+            .collect();
+
+        Ok((solution, redundant))
+    }
+
+    /// Gets every puzzle one clue change away from this one that still has a unique solution.
+    /// Each given is tried with every other number that doesn't conflict with its row, col, or house,
+    /// returning the changed coord and number along with the resulting solution.
+    pub fn neighbors_by_clue_change(&self) -> Vec<(Coord, u8, Sudoku)> {
+        let mut neighbors = Vec::new();
+
+        for coord in self.coords() {
+            let Some(given) = self.get(coord) else {
+                continue;
+            };
+
+            let mut sudoku = self.clone();
+            sudoku.unset(coord);
+
+            let alternatives = sudoku.get_sorted_possible_numbers(coord);
+            for n in alternatives.into_iter().filter(|&n| n != given) {
+                sudoku.set(coord, n);
+                if sudoku.count_solutions(2) == 1 {
+                    if let Ok(solution) = sudoku.solve() {
+                        neighbors.push((coord, n, solution));
+                    }
+                }
+            }
+        }
+
+        neighbors
+    }
+
+    /// Solves the sudoku, only allowing each empty cell to be one of the player's pencil marks for it,
+    /// indexed by row then col. Returns `InvalidPencilMarks` if the marks aren't the same size as
+    /// the grid, `DuplicateGiven` for repeated givens, or `Unsolvable` if the pencil marks rule out
+    /// every solution.
+    pub fn solve_with_pencil_marks<R: AsRef<[HashSet<u8>]>>(
+        &self,
+        marks: &[R],
+    ) -> Result<Self, InvalidSudokuError> {
+        let side = self.side() as usize;
+        if marks.len() != side || marks.iter().any(|row| row.as_ref().len() != side) {
+            return Err(InvalidSudokuError::InvalidPencilMarks);
+        }
+        if self.check_valid().is_err() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        let mut sudoku = self.clone();
+
+        if sudoku.solve_marked_rec(marks) {
+            Ok(sudoku)
+        } else {
+            Err(InvalidSudokuError::Unsolvable)
+        }
+    }
+
+    fn solve_marked_rec<R: AsRef<[HashSet<u8>]>>(&mut self, marks: &[R]) -> bool {
+        // Find the empty cell with the fewest numbers left once the marks are applied.
+        let mut best: Option<(Coord, HashSet<u8>)> = None;
+        for coord in self.coords() {
+            if self.get(coord).is_some() {
+                continue;
+            }
+
+            let possible = &self.get_possible_numbers(coord)
+                & &marks[coord.row as usize].as_ref()[coord.col as usize];
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_possible)| possible.len() < best_possible.len())
+            {
+                best = Some((coord, possible));
+            }
+        }
+
+        let Some((coord, possible)) = best else {
+            return self.validate().is_ok();
+        };
+
+        let mut possible = possible.into_iter().collect::<Vec<u8>>();
+        possible.sort();
+
+        for n in possible {
+            self.set(coord, n);
+            if self.solve_marked_rec(marks) {
+                return true;
+            }
+        }
+
+        self.unset(coord);
+        false
+    }
+
+    /// Solves the sudoku, trying each cell's possible numbers in an order shuffled from the seed.
+    /// A puzzle with a unique solution always gives the same result, otherwise different seeds
+    /// can give different solutions.
+    pub fn solve_with_seed(&self, seed: u64) -> Result<Self, InvalidSudokuError> {
+        let mut sudoku = self.clone();
+        let mut rng = Rng(seed);
+
+        if sudoku.solve_seeded_rec(&mut rng) {
+            Ok(sudoku)
+        } else {
+            Err(InvalidSudokuError::Unsolvable)
+        }
+    }
+
+    /// Generates a complete, valid grid shuffled from the seed, the same seed always giving the
+    /// same grid.
+    pub fn generate_solved(seed: u64) -> Self {
+        let size = Sudoku::SIZE as usize;
+        let mut sudoku = Sudoku::new(vec![vec![None; size]; size]);
+        // An empty grid can always be filled in.
+        sudoku.solve_seeded_rec(&mut Rng(seed));
+        sudoku
+    }
+
+    /// Generates a puzzle with a unique solution by removing clues from `generate_solved` in an order
+    /// shuffled from the seed, skipping any removal that gives more than one solution. Stops at
+    /// `target_clues`, or before that once no more clues can be removed.
+    pub fn generate_puzzle(seed: u64, target_clues: usize) -> Self {
+        let mut rng = Rng(seed);
+        let mut sudoku = Sudoku::generate_solved(rng.next_u64());
+
+        let mut coords = sudoku.coords().collect::<Vec<Coord>>();
+        rng.shuffle(&mut coords);
+
+        for coord in coords {
+            if sudoku.clue_count() <= target_clues {
+                break;
+            }
+
+            let Some(n) = sudoku.get(coord) else {
+                continue;
+            };
+            sudoku.unset(coord);
+            if sudoku.count_solutions(2) != 1 {
+                sudoku.set(coord, n);
+            }
+        }
+
+        sudoku
+    }
+
+    /// Masks a solved grid into a puzzle of the target difficulty. Clues are removed in an order
+    /// shuffled from the seed, skipping any removal that loses the unique solution or makes the
+    /// puzzle harder than the target, so it ends up as sparse as it can be at that difficulty.
+    /// Returns `None` if the grid isn't a valid solution or the target can't be reached from it.
+    pub fn mask_to_difficulty(&self, target: Difficulty, seed: u64) -> Option<Sudoku> {
+        if !self.is_complete() || self.validate().is_err() {
+            return None;
+        }
+
+        let mut rng = Rng(seed);
+        let mut coords = self.coords().collect::<Vec<Coord>>();
+        rng.shuffle(&mut coords);
+
+        let mut puzzle = self.clone();
+        for coord in coords {
+            let Some(n) = puzzle.get(coord) else {
+                continue;
+            };
+
+            puzzle.unset(coord);
+            if !puzzle.has_unique_solution() || puzzle.difficulty() > target {
+                puzzle.set(coord, n);
+            }
+        }
+
+        (puzzle.difficulty() == target).then_some(puzzle)
+    }
+
+    /// Generates a pack of distinct puzzles with unique solutions, the given number at each
+    /// difficulty in the order of the spec. Puzzles are made with `generate_puzzle` down to a clue
+    /// count that usually lands on the difficulty, from seeds drawn from `seed`, until enough of them
+    /// rate right. Each difficulty gets 20 tries per puzzle asked for, and if they run out the pack
+    /// is left short of that difficulty rather than trying forever.
+    pub fn generate_pack(spec: &[(Difficulty, usize)], seed: u64) -> Vec<Sudoku> {
+        const ATTEMPTS_PER_PUZZLE: usize = 20;
+
+        Sudoku::generate_pack_within(spec, seed, ATTEMPTS_PER_PUZZLE)
+    }
+
+    fn generate_pack_within(
+        spec: &[(Difficulty, usize)],
+        seed: u64,
+        attempts_per_puzzle: usize,
+    ) -> Vec<Sudoku> {
+        let mut rng = Rng(seed);
+        let mut pack: Vec<Sudoku> = Vec::new();
+
+        for &(difficulty, count) in spec {
+            let target_clues = match difficulty {
+                Difficulty::Trivial => Sudoku::CELL_COUNT,
+                Difficulty::Easy => 45,
+                Difficulty::Medium => 30,
+                Difficulty::Hard => 0,
+            };
+
+            let (mut found, mut attempts) = (0, 0);
+            while found < count && attempts < count * attempts_per_puzzle {
+                attempts += 1;
+                let puzzle = Sudoku::generate_puzzle(rng.next_u64(), target_clues);
+                if puzzle.difficulty() == difficulty && !pack.contains(&puzzle) {
+                    pack.push(puzzle);
+                    found += 1;
+                }
+            }
+        }
+
+        pack
+    }
+
+    /// Generates a puzzle with a unique solution whose `hardest_technique` is the given one, for
+    /// teaching it. Puzzles are made with `generate_puzzle` from seeds drawn from `seed` until one
+    /// rates right, which can take a while for the harder techniques. Returns `None` if none turns
+    /// up in 500 tries.
+    pub fn generate_requiring(technique: Technique, seed: u64) -> Option<Self> {
+        const ATTEMPTS: usize = 500;
+
+        // Removing as many clues as possible makes harder techniques likelier, but leaves few
+        // puzzles that singles alone can finish.
+        let target_clues = match technique {
+            Technique::NakedSingles => 45,
+            Technique::HiddenSingles => 30,
+            _ => 0,
+        };
+
+        let mut rng = Rng(seed);
+        (0..ATTEMPTS)
+            .map(|_| Sudoku::generate_puzzle(rng.next_u64(), target_clues))
+            .find(|puzzle| puzzle.hardest_technique() == Some(technique))
+    }
+
+    /// Generates a puzzle with a unique solution that takes `solve_counted` about `target` guesses,
+    /// within a quarter of it either way (and at least one). Clues are removed like
+    /// `generate_puzzle` until the guesses are close enough, trying new grids from the seed when one
+    /// runs out of clues to remove. Returns `None` if no close enough puzzle turns up.
+    pub fn generate_with_guess_count(target: usize, seed: u64) -> Option<Self> {
+        const ATTEMPTS: usize = 20;
+
+        let tolerance = (target / 4).max(1);
+        let guesses = |sudoku: &Sudoku| {
+            sudoku
+                .solve_counted()
+                .map_or(usize::MAX, |(_, counters)| counters.placements)
+        };
+
+        let mut rng = Rng(seed);
+        for _ in 0..ATTEMPTS {
+            let mut sudoku = Sudoku::generate_solved(rng.next_u64());
+            let mut coords = sudoku.coords().collect::<Vec<Coord>>();
+            rng.shuffle(&mut coords);
+
+            for coord in coords {
+                let Some(n) = sudoku.get(coord) else {
+                    continue;
+                };
+                sudoku.unset(coord);
+                if sudoku.count_solutions(2) != 1 {
+                    sudoku.set(coord, n);
+                    continue;
+                }
+
+                let guesses = guesses(&sudoku);
+                if guesses.abs_diff(target) <= tolerance {
+                    return Some(sudoku);
+                }
+                // Removing more clues tends to need more guessing, put it back rather than overshoot.
+                if guesses > target {
+                    sudoku.set(coord, n);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn solve_seeded_rec(&mut self, rng: &mut Rng) -> bool {
+        let Some(coord) = self.most_constrained_cell() else {
+            return self.validate().is_ok();
+        };
+
+        // Sort first, so the shuffle doesn't depend on the hash set's order.
+        let mut possible = self.get_sorted_possible_numbers(coord);
+        rng.shuffle(&mut possible);
+
+        for n in possible {
+            self.set(coord, n);
+            if self.solve_seeded_rec(rng) {
+                return true;
+            }
+        }
+
+        self.unset(coord);
+        false
+    }
+
+    /// Attempts to repair a scanned grid with a single misread cell. If the grid doesn't have a
+    /// unique solution, each filled cell is tried as a visually similar number and as empty,
+    /// returning the first single edit that gives a unique solution. Only single-cell edits are
+    /// tried, so grids with more than one misread cell won't be repaired.
+    pub fn repair_ocr(&self) -> Option<Self> {
+        if self.count_solutions(2) == 1 {
+            return Some(self.clone());
+        }
+
+        fn similar(n: u8) -> &'static [u8] {
+            match n {
+                1 => &[7, 4],
+                2 => &[7],
+                3 => &[8, 5],
+                4 => &[1, 9],
+                5 => &[6, 3],
+                6 => &[5, 8],
+                7 => &[1, 2],
+                8 => &[3, 6, 9],
+                9 => &[8, 4],
+                _ => &[],
+            }
+        }
+
+        for coord in self.coords() {
+            let Some(n) = self.get(coord) else {
+                continue;
+            };
+
+            let mut sudoku = self.clone();
+            let edits = similar(n).iter().map(|&n| Some(n)).chain([None]);
+            for edit in edits {
+                sudoku[coord] = edit;
+                if sudoku.count_solutions(2) == 1 {
+                    return Some(sudoku);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Same as `solve`, but once propagation runs out the most constrained cell's possible numbers are
+    /// each searched in parallel, returning whichever solution turns up first. For a puzzle with a
+    /// unique solution that's the same as `solve`.
+    #[cfg(feature = "rayon")]
+    pub fn solve_parallel(&self) -> Result<Self, InvalidSudokuError> {
+        use rayon::prelude::*;
+
+        if self.has_duplicates() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        let mut sudoku = self.clone();
+        sudoku.propagate()?;
+
+        let Some(coord) = sudoku.most_constrained_cell() else {
+            return match sudoku.validate() {
+                Ok(_) => Ok(sudoku),
+                Err(_) => Err(InvalidSudokuError::Unsolvable),
+            };
+        };
+
+        sudoku
+            .get_sorted_possible_numbers(coord)
+            .into_par_iter()
+            .find_map_any(|n| {
+                let mut guess = sudoku.clone();
+                guess.set(coord, n);
+                guess.solve().ok()
+            })
+            .ok_or(InvalidSudokuError::Unsolvable)
+    }
+
+    /// Checks each puzzle for solvability and uniqueness, in the same order as given.
+    pub fn validate_batch(puzzles: &[Sudoku]) -> Vec<PuzzleStatus> {
+        puzzles
+            .iter()
+            .map(|puzzle| {
+                let solutions = puzzle.count_solutions(2);
+                PuzzleStatus {
+                    solvable: solutions > 0,
+                    unique: solutions == 1,
+                    clue_count: puzzle.clue_count(),
+                }
+            })
+            .collect()
+    }
+
+    /// Sums up a batch of puzzles, rating and solving each of them, in parallel with the `rayon`
+    /// feature. An empty batch is all zeros.
+    pub fn batch_stats(puzzles: &[Sudoku]) -> BatchStats {
+        let analyze = |puzzle: &Sudoku| {
+            let nodes = puzzle
+                .solve_counted()
+                .ok()
+                .map(|(_, counters)| counters.nodes);
+            (puzzle.clue_count(), puzzle.difficulty(), nodes)
+        };
+        #[cfg(feature = "rayon")]
+        let analyses = {
+            use rayon::prelude::*;
+            puzzles.par_iter().map(analyze).collect::<Vec<_>>()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let analyses = puzzles.iter().map(analyze).collect::<Vec<_>>();
+
+        let mean = |total: usize, count: usize| total as f32 / count.max(1) as f32;
+        let mut difficulty_histogram = HashMap::new();
+        for &(_, difficulty, _) in &analyses {
+            *difficulty_histogram.entry(difficulty).or_insert(0) += 1;
+        }
+        let guessing = difficulty_histogram
+            .get(&Difficulty::Hard)
+            .copied()
+            .unwrap_or(0);
+        let nodes = analyses
+            .iter()
+            .filter_map(|&(_, _, nodes)| nodes)
+            .collect::<Vec<usize>>();
+
+        BatchStats {
+            average_clues: mean(
+                analyses.iter().map(|&(clues, _, _)| clues).sum(),
+                puzzles.len(),
+            ),
+            difficulty_histogram,
+            percent_guessing: 100.0 * mean(guessing, puzzles.len()),
+            average_nodes: mean(nodes.iter().sum(), nodes.len()),
+        }
+    }
+
+    /// Solves one puzzle per line of `input` (a line of cells, as in `from_line`), in order,
+    /// skipping blank lines. Each puzzle gets its own result, so a line that can't be parsed or
+    /// solved doesn't stop the rest.
+    pub fn solve_many(input: &str) -> Vec<Result<Sudoku, BatchError>> {
+        input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                Sudoku::from_line(line)
+                    .map_err(BatchError::Parse)
+                    .and_then(|puzzle| puzzle.solve().map_err(BatchError::Solve))
+            })
+            .collect()
+    }
+
+    /// Same as `solve_many`, but reads `input` a line at a time, writing each solution to `output`
+    /// on a line of its own. A line that can't be parsed or solved gets `error: ` and the error in
+    /// its place. Output is flushed every 100 puzzles.
+    pub fn solve_stream<R: BufRead, W: Write>(input: R, mut output: W) -> io::Result<()> {
+        const FLUSH_EVERY: usize = 100;
+
+        let mut solved = 0;
+        for line in input.lines() {
+            for result in Sudoku::solve_many(&line?) {
+                match result {
+                    Ok(solution) => writeln!(output, "{}", solution.to_line())?,
+                    Err(error) => writeln!(output, "error: {error}")?,
+                }
+
+                solved += 1;
+                if solved % FLUSH_EVERY == 0 {
+                    output.flush()?;
+                }
+            }
+        }
+
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cage, ParseSudokuError};
+
+    #[test]
+    fn test_solve_stream() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let line = Sudoku::to_line;
+
+        let input = format!("{}\n\n{}\nnot a puzzle\n", line(&easy), line(&solved));
+        let mut output = Vec::new();
+        Sudoku::solve_stream(input.as_bytes(), &mut output).unwrap();
+
+        let expected = format!(
+            "{}\n{}\nerror: couldn't parse puzzle: puzzle isn't 4x4, 9x9, or 16x16\n",
+            line(&solved),
+            line(&solved)
+        );
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+
+    #[test]
+    fn test_solve_many() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        // A second 9 in the top row.
+        let mut unsolvable = easy.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+
+        let input = format!(
+            "{}\r\n{}\n\n{}\nnot a puzzle",
+            easy.to_line(),
+            unsolvable.to_line(),
+            solved.to_line()
+        );
+        let results = Sudoku::solve_many(&input);
+        assert_eq!(4, results.len());
+        assert_eq!(Some(&solved), results[0].as_ref().ok());
+        assert!(matches!(
+            results[1],
+            Err(BatchError::Solve(InvalidSudokuError::DuplicateGiven))
+        ));
+        assert_eq!(Some(&solved), results[2].as_ref().ok());
+        assert!(matches!(
+            results[3],
+            Err(BatchError::Parse(ParseSudokuError::InvalidSize))
+        ));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_solve_parallel() {
+        for puzzle in [include_str!("easy.txt"), include_str!("escargot.txt")] {
+            let sudoku = puzzle.parse::<Sudoku>().unwrap();
+            assert_eq!(sudoku.solve().unwrap(), sudoku.solve_parallel().unwrap());
+        }
+
+        // A second 9 in the top row.
+        let mut unsolvable = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert_eq!(
+            InvalidSudokuError::DuplicateGiven,
+            unsolvable.solve_parallel().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_count_solutions() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(1, sudoku.count_solutions(2));
+
+        // An empty grid has plenty, but counting stops at the limit.
+        let empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        assert_eq!(2, empty.count_solutions(2));
+        assert_eq!(10, empty.count_solutions(10));
+
+        // A second 9 in the top row.
+        let mut unsolvable = sudoku.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert_eq!(0, unsolvable.count_solutions(2));
+    }
+
+    #[test]
+    fn test_count_solutions_with_variants() {
+        // Several standard solutions, but only one has every number once along both diagonals.
+        let standard = include_str!("diagonal.txt").parse::<Sudoku>().unwrap();
+        let diagonal = standard.clone().with_diagonals();
+        assert!(standard.count_solutions(10) > 1);
+        assert_eq!(1, diagonal.count_solutions(10));
+        assert!(!standard.has_unique_solution());
+        assert!(diagonal.has_unique_solution());
+        assert_eq!(1, diagonal.solutions().count());
+
+        // The other way around, this one's only standard solution repeats along a diagonal.
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(easy.has_unique_solution());
+        assert_eq!(0, easy.clone().with_diagonals().count_solutions(2));
+
+        // A rectangle of two numbers that can be swapped, until a cage says which way round.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let mut rectangle = solved.clone();
+        for (row, col) in [(2, 1), (2, 2), (4, 1), (4, 2)] {
+            rectangle.unset(Coord { row, col });
+        }
+        assert_eq!(2, rectangle.count_solutions(10));
+        let (a, b) = (Coord { row: 2, col: 1 }, Coord { row: 2, col: 0 });
+        rectangle.add_cage(Cage {
+            cells: vec![a, b],
+            sum: solved.get(a).unwrap() + solved.get(b).unwrap(),
+        });
+        assert_eq!(1, rectangle.count_solutions(10));
+        assert!(rectangle.has_unique_solution());
+    }
+
+    #[test]
+    fn test_solutions() {
+        let size = Sudoku::SIZE as usize;
+        let mut sudoku = Sudoku::new(vec![vec![None; size]; size]);
+        sudoku.set(Coord { row: 4, col: 4 }, 5);
+
+        let solutions = sudoku.solutions().take(10).collect::<Vec<Sudoku>>();
+        assert_eq!(10, solutions.len());
+        for (i, solution) in solutions.iter().enumerate() {
+            assert!(solution.validate().is_ok());
+            assert!(sudoku.solution_preserves_givens(solution));
+            assert!(!solutions[..i].contains(solution));
+        }
+
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(vec![expected], easy.solutions().collect::<Vec<Sudoku>>());
+
+        // A second 9 in the top row.
+        let mut unsolvable = easy.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert_eq!(None, unsolvable.solutions().next());
+    }
+
+    #[test]
+    fn test_solve_with_confidence() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!((solved.clone(), 1.0), easy.solve_with_confidence().unwrap());
+
+        // These four cells form a rectangle of two numbers that can be swapped.
+        let mut rectangle = solved.clone();
+        for (row, col) in [(2, 1), (2, 2), (4, 1), (4, 2)] {
+            rectangle.unset(Coord { row, col });
+        }
+        let (solution, confidence) = rectangle.solve_with_confidence().unwrap();
+        assert!(rectangle.solution_preserves_givens(&solution));
+        assert_eq!(0.5, confidence);
+
+        let empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        assert_eq!(0.1, empty.solve_with_confidence().unwrap().1);
+
+        // A second 9 in the top row.
+        let mut unsolvable = easy.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 9);
+        assert!(unsolvable.solve_with_confidence().is_err());
+    }
+
+    #[test]
+    fn test_is_clue_redundant() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.is_clue_redundant(Coord { row: 0, col: 8 }));
+        assert!(!sudoku.is_clue_redundant(Coord { row: 1, col: 4 }));
+        // Not a clue
+        assert!(!sudoku.is_clue_redundant(Coord { row: 0, col: 0 }));
+    }
+
+    #[test]
+    fn test_is_proper() {
+        // The easy puzzle with its redundant clues taken out until none were left.
+        let minimal = include_str!("minimal.txt").parse::<Sudoku>().unwrap();
+        assert!(minimal.has_unique_solution());
+        assert!(minimal.is_proper());
+
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.has_unique_solution());
+        assert!(!sudoku.is_proper());
+
+        let mut multiple = minimal.clone();
+        multiple.unset(Coord { row: 0, col: 1 });
+        assert!(!multiple.has_unique_solution());
+        assert!(!multiple.is_proper());
+    }
+
+    #[test]
+    fn test_unavoidable_sets() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let sets = solved.unavoidable_sets(6);
+
+        // A rectangle of 6s and 7s that can be swapped.
+        let rectangle = vec![
+            Coord { row: 2, col: 1 },
+            Coord { row: 2, col: 2 },
+            Coord { row: 4, col: 1 },
+            Coord { row: 4, col: 2 },
+        ];
+        assert!(sets.contains(&rectangle));
+
+        for (i, set) in sets.iter().enumerate() {
+            assert!(set.len() <= 6);
+            let mut sudoku = solved.clone();
+            for &coord in set {
+                sudoku.unset(coord);
+            }
+            assert!(!sudoku.has_unique_solution());
+            assert!(!sets[..i]
+                .iter()
+                .any(|smaller| smaller.iter().all(|coord| set.contains(coord))));
+        }
+
+        // The puzzle itself gives the same sets as its solution.
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(sets, easy.unavoidable_sets(6));
+
+        // Cutting the search off at a smaller size finds just the smaller sets.
+        let small = sets
+            .iter()
+            .filter(|set| set.len() <= 4)
+            .cloned()
+            .collect::<Vec<Vec<Coord>>>();
+        assert_eq!(small, solved.unavoidable_sets(4));
+    }
+
+    #[test]
+    fn test_solve_with_redundancy() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let (solution, redundant) = sudoku.solve_with_redundancy().unwrap();
+        assert_eq!(expected, solution);
+        assert!(redundant.contains(&Coord { row: 0, col: 8 }));
+        assert!(!redundant.contains(&Coord { row: 1, col: 4 }));
+    }
+
+    #[test]
+    fn test_neighbors_by_clue_change() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let neighbors = sudoku.neighbors_by_clue_change();
+        assert!(!neighbors.is_empty());
+
+        for (coord, n, solution) in neighbors {
+            assert_ne!(sudoku.get(coord), Some(n));
+            assert_eq!(Some(n), solution.get(coord));
+            assert!(solution.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_solve_with_pencil_marks() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let mut marks: [[HashSet<u8>; 9]; 9] =
+            std::array::from_fn(|_| std::array::from_fn(|_| (1..=9).collect()));
+        assert_eq!(expected, sudoku.solve_with_pencil_marks(&marks).unwrap());
+
+        // Leave the actual answer for the top-left cell out of its marks.
+        marks[0][0].remove(&8);
+        assert_eq!(
+            InvalidSudokuError::Unsolvable,
+            sudoku.solve_with_pencil_marks(&marks).unwrap_err()
+        );
+
+        // Marks that don't cover the grid.
+        let no_marks: Vec<Vec<HashSet<u8>>> = Vec::new();
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidPencilMarks),
+            sudoku.solve_with_pencil_marks(&no_marks)
+        );
+        let short_row = vec![vec![(1..=9).collect::<HashSet<u8>>(); 8]; 9];
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidPencilMarks),
+            sudoku.solve_with_pencil_marks(&short_row)
+        );
+
+        // A second 9 in the top row, caught before searching.
+        let mut repeated = sudoku.clone();
+        repeated.set(Coord { row: 0, col: 0 }, 9);
+        let marks: [[HashSet<u8>; 9]; 9] =
+            std::array::from_fn(|_| std::array::from_fn(|_| (1..=9).collect()));
+        assert_eq!(
+            Err(InvalidSudokuError::DuplicateGiven),
+            repeated.solve_with_pencil_marks(&marks)
+        );
+    }
+
+    #[test]
+    fn test_solve_with_seed() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        for seed in 0..100 {
+            assert_eq!(expected, sudoku.solve_with_seed(seed).unwrap());
+        }
+
+        // An empty grid has plenty of solutions to pick from.
+        let empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        let a = empty.solve_with_seed(1).unwrap();
+        let b = empty.solve_with_seed(2).unwrap();
+        assert!(a.validate().is_ok());
+        assert!(b.validate().is_ok());
+        assert_ne!(a, b);
+        assert_eq!(a, empty.solve_with_seed(1).unwrap());
+    }
+
+    #[test]
+    fn test_generate_solved() {
+        let a = Sudoku::generate_solved(7);
+        assert!(a.validate().is_ok());
+        assert_eq!(a, Sudoku::generate_solved(7));
+        assert_ne!(a, Sudoku::generate_solved(8));
+    }
+
+    /// Whether solving the puzzle goes wrong, no solution or one that's invalid or changes a given.
+    fn solve_fails(puzzle: &Sudoku) -> bool {
+        puzzle.solve().map_or(true, |solution| {
+            solution.validate().is_err() || !puzzle.agrees_with(&solution)
+        })
+    }
+
+    /// Fills back in as many of the removed cells as it can while the puzzle still fails, so a
+    /// failure shows the fewest blanks it takes.
+    fn shrink_failure(mut puzzle: Sudoku, solved: &Sudoku) -> Sudoku {
+        for coord in solved.coords() {
+            if puzzle.get(coord).is_some() {
+                continue;
+            }
+
+            let mut smaller = puzzle.clone();
+            smaller.set(coord, solved.get(coord).unwrap());
+            if solve_fails(&smaller) {
+                puzzle = smaller;
+            }
+        }
+        puzzle
+    }
+
+    #[test]
+    fn test_solve_random_removals() {
+        // Every amount of blanks from none to all of them, nearly empty and single blanks included.
+        const CASES: u64 = 82;
+
+        let mut rng = Rng(295);
+        for case in 0..CASES {
+            let solved = Sudoku::generate_solved(rng.next_u64());
+            let mut coords = solved.coords().collect::<Vec<Coord>>();
+            rng.shuffle(&mut coords);
+
+            let mut puzzle = solved.clone();
+            for &coord in &coords[..case as usize] {
+                puzzle.unset(coord);
+            }
+
+            if solve_fails(&puzzle) {
+                let minimal = shrink_failure(puzzle, &solved);
+                panic!(
+                    "solve failed with {case} blanks, shrunk to {}",
+                    minimal.to_line()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_has_unique_solution() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert!(solved.has_unique_solution());
+
+        // Two cells on their own are always forced by the rest of their row.
+        let mut two_empty = solved.clone();
+        two_empty.unset(Coord { row: 2, col: 1 });
+        two_empty.unset(Coord { row: 4, col: 2 });
+        assert!(two_empty.has_unique_solution());
+
+        // These four cells form a rectangle of two numbers that can be swapped.
+        let mut rectangle = two_empty.clone();
+        rectangle.unset(Coord { row: 2, col: 2 });
+        rectangle.unset(Coord { row: 4, col: 1 });
+        assert!(!rectangle.has_unique_solution());
+
+        let mut repeated = solved.clone();
+        repeated.set(
+            Coord { row: 0, col: 0 },
+            solved.get(Coord { row: 0, col: 1 }).unwrap(),
+        );
+        assert!(!repeated.has_unique_solution());
+
+        for grid in [&solved, &two_empty, &rectangle, &repeated] {
+            assert_eq!(
+                grid.has_unique_solution(),
+                grid.is_sole_completion_of_own_givens()
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_puzzle() {
+        let puzzle = Sudoku::generate_puzzle(3, 30);
+        assert_eq!(30, puzzle.clue_count());
+        assert_eq!(1, puzzle.count_solutions(2));
+
+        let solution = puzzle.solve().unwrap();
+        assert!(puzzle.solution_preserves_givens(&solution));
+    }
+
+    #[test]
+    fn test_generate_with_guess_count() {
+        let puzzle = Sudoku::generate_with_guess_count(2, 1).unwrap();
+        assert_eq!(1, puzzle.count_solutions(2));
+
+        let (_, counters) = puzzle.solve_counted().unwrap();
+        assert!(counters.placements.abs_diff(2) <= 1);
+    }
+
+    #[test]
+    fn test_mask_to_difficulty() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        for target in [Difficulty::Easy, Difficulty::Medium] {
+            let puzzle = solved.mask_to_difficulty(target, 210).unwrap();
+            assert_eq!(target, puzzle.difficulty());
+            assert!(puzzle.has_unique_solution());
+            assert!(puzzle.agrees_with(&solved));
+        }
+
+        // Already as easy as it gets.
+        assert_eq!(
+            Some(solved.clone()),
+            solved.mask_to_difficulty(Difficulty::Trivial, 210)
+        );
+
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(None, easy.mask_to_difficulty(Difficulty::Easy, 210));
+    }
+
+    #[test]
+    fn test_generate_requiring() {
+        for technique in [Technique::HiddenSingles, Technique::XCycles] {
+            let puzzle = Sudoku::generate_requiring(technique, 0).unwrap();
+            assert!(puzzle.has_unique_solution());
+            assert_eq!(Some(technique), puzzle.hardest_technique());
+
+            // Capped at the technique before it, logic gets stuck.
+            let rank = Technique::ALL.iter().position(|&t| t == technique).unwrap();
+            assert_eq!(
+                Err(InvalidSudokuError::NeedsHarderTechnique),
+                puzzle.solve_logic_only(Technique::ALL[rank - 1])
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_pack() {
+        let spec = [
+            (Difficulty::Easy, 2),
+            (Difficulty::Medium, 1),
+            (Difficulty::Hard, 1),
+        ];
+        let pack = Sudoku::generate_pack(&spec, 5);
+
+        let expected = [
+            Difficulty::Easy,
+            Difficulty::Easy,
+            Difficulty::Medium,
+            Difficulty::Hard,
+        ];
+        assert_eq!(expected.len(), pack.len());
+        for (i, (puzzle, difficulty)) in pack.iter().zip(expected).enumerate() {
+            assert_eq!(difficulty, puzzle.difficulty());
+            assert!(puzzle.has_unique_solution());
+            assert!(!pack[..i].contains(puzzle));
+        }
+
+        // With a single try each, some don't come out hard, and the pack is left short.
+        let pack = Sudoku::generate_pack_within(&[(Difficulty::Hard, 3)], 5, 1);
+        assert_eq!(2, pack.len());
+        assert!(pack
+            .iter()
+            .all(|puzzle| puzzle.difficulty() == Difficulty::Hard));
+    }
+
+    #[test]
+    fn test_repair_ocr() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(Some(sudoku.clone()), sudoku.repair_ocr());
+
+        // The 8 was misread as a 3.
+        let mut scanned = sudoku.clone();
+        scanned.set(Coord { row: 1, col: 5 }, 3);
+        assert_eq!(Some(sudoku), scanned.repair_ocr());
+    }
+
+    #[test]
+    fn test_count_completions_with_fixed() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        sudoku.unset(Coord { row: 1, col: 4 });
+        sudoku.unset(Coord { row: 1, col: 5 });
+
+        let all = sudoku.count_completions_with_fixed(&[]);
+        assert!(all > 1);
+
+        let fixed = sudoku.count_completions_with_fixed(&[(Coord { row: 1, col: 4 }, 6)]);
+        assert!(fixed >= 1);
+        assert!(fixed < all);
+
+        let conflicting = sudoku.count_completions_with_fixed(&[(Coord { row: 0, col: 1 }, 1)]);
+        assert_eq!(0, conflicting);
+    }
+
+    #[test]
+    fn test_batch_stats() {
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let escargot = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let batch = [
+            singles.clone(),
+            easy.clone(),
+            escargot.clone(),
+            escargot.clone(),
+        ];
+
+        let stats = Sudoku::batch_stats(&batch);
+        assert_eq!(
+            batch.len(),
+            stats.difficulty_histogram.values().sum::<usize>()
+        );
+        assert_eq!(Some(&2), stats.difficulty_histogram.get(&Difficulty::Hard));
+        assert_eq!(Some(&1), stats.difficulty_histogram.get(&Difficulty::Easy));
+        assert_eq!(50.0, stats.percent_guessing);
+
+        let clues = batch.iter().map(Sudoku::clue_count).sum::<usize>();
+        assert_eq!(clues as f32 / 4.0, stats.average_clues);
+        let nodes = batch
+            .iter()
+            .map(|puzzle| puzzle.solve_counted().unwrap().1.nodes)
+            .sum::<usize>();
+        assert_eq!(nodes as f32 / 4.0, stats.average_nodes);
+
+        let empty = Sudoku::batch_stats(&[]);
+        assert!(empty.difficulty_histogram.is_empty());
+        assert_eq!(0.0, empty.average_clues);
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        let unique = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let mut not_unique = unique.clone();
+        not_unique.unset(Coord { row: 1, col: 4 });
+        let mut unsolvable = unique.clone();
+        unsolvable.set(Coord { row: 0, col: 0 }, 1);
+
+        let result = Sudoku::validate_batch(&[unique, not_unique, unsolvable]);
+        let expected = vec![
+            PuzzleStatus {
+                solvable: true,
+                unique: true,
+                clue_count: 36,
+            },
+            PuzzleStatus {
+                solvable: true,
+                unique: false,
+                clue_count: 35,
+            },
+            PuzzleStatus {
+                solvable: false,
+                unique: false,
+                clue_count: 37,
+            },
+        ];
+        assert_eq!(expected, result);
+    }
+}
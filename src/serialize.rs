@@ -0,0 +1,84 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Coord, HintReason, InvalidSudokuError, Sudoku};
+
+/// One placement of `solve_trace_json`, `technique` being `null` for a guess.
+#[derive(Serialize)]
+struct TraceStep {
+    coord: Coord,
+    value: u8,
+    technique: Option<HintReason>,
+    is_guess: bool,
+}
+
+/// Serialized as its rows of cells, `null` for an empty cell. Restrictions aren't included.
+impl Serialize for Sudoku {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.grid.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sudoku {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let grid = Vec::<Vec<Option<u8>>>::deserialize(deserializer)?;
+        Sudoku::from_grid(grid).map_err(de::Error::custom)
+    }
+}
+
+impl Sudoku {
+    /// Solves the sudoku, returning its `solve_certified` placements in order as a JSON array of
+    /// `{"coord", "value", "technique", "is_guess"}` objects for a frontend to replay.
+    pub fn solve_trace_json(&self) -> Result<String, InvalidSudokuError> {
+        let (_, cert) = self.solve_certified()?;
+        let steps = cert
+            .steps
+            .into_iter()
+            .map(|(coord, value, technique)| TraceStep {
+                coord,
+                value,
+                technique,
+                is_guess: technique.is_none(),
+            })
+            .collect::<Vec<_>>();
+        Ok(serde_json::to_string(&steps).expect("trace steps always serialize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Coord;
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let json = serde_json::to_string(&sudoku).unwrap();
+        assert!(json.starts_with("[[null,9,null,"));
+        assert_eq!(sudoku, serde_json::from_str::<Sudoku>(&json).unwrap());
+
+        let coord = Coord { row: 1, col: 2 };
+        let json = serde_json::to_string(&coord).unwrap();
+        assert_eq!(coord, serde_json::from_str::<Coord>(&json).unwrap());
+
+        assert!(serde_json::from_str::<Sudoku>("[[1,2],[3,4]]").is_err());
+    }
+
+    #[test]
+    fn test_solve_trace_json() {
+        for puzzle in [include_str!("easy.txt"), include_str!("escargot.txt")] {
+            let sudoku = puzzle.parse::<Sudoku>().unwrap();
+            let json = sudoku.solve_trace_json().unwrap();
+            let steps = serde_json::from_str::<Vec<serde_json::Value>>(&json).unwrap();
+            assert_eq!(sudoku.empty_cells(), steps.len());
+            for step in &steps {
+                assert!(step["coord"]["row"].is_u64() && step["value"].is_u64());
+                assert_eq!(step["technique"].is_null(), step["is_guess"]);
+            }
+        }
+
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let json = easy.solve_trace_json().unwrap();
+        assert!(json.starts_with(r#"[{"coord":{"row":"#));
+        assert!(!json.contains(r#""is_guess":true"#));
+    }
+}
@@ -0,0 +1,1674 @@
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
+
+use crate::{Adjacency, Cage, Coord, InvalidSudokuError, ParseSudokuError, Unit};
+
+/// Box sizes a grid can have, for 4x4, 9x9, and 16x16 grids.
+pub(crate) const BOX_SIZES: [u8; 3] = [2, 3, 4];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sudoku {
+    pub(crate) grid: Vec<Vec<Option<u8>>>,
+    /// The width (and height) of a house, the grid is `box_size * box_size` cells on a side.
+    pub(crate) box_size: u8,
+    /// The numbers each cell is allowed to be as a bitmask like `possible_mask`, for odd/even style
+    /// puzzles. Unrestricted cells allow every number.
+    pub(crate) restrictions: Vec<Vec<u32>>,
+    /// Rules between pairs of adjacent cells from `constrain_adjacent`, none for a standard puzzle.
+    pub(crate) adjacency: Vec<(Coord, Coord, Adjacency)>,
+    /// Whether both main diagonals also need every number once, from `with_diagonals`.
+    pub(crate) diagonals: bool,
+    /// Killer sudoku cages from `add_cage`, none for a standard puzzle.
+    pub(crate) cages: Vec<Cage>,
+}
+
+/// Counts of the work a search did, from `solve_counted`. Puzzles with the same solution can take
+/// very different amounts of work, so they're also a measure of how hard a puzzle is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfCounters {
+    /// Cells the search visited, the count `solve_with_budget` limits.
+    pub nodes: usize,
+    /// Empty cells whose possible numbers were worked out to pick the next cell to fill.
+    pub candidate_evaluations: usize,
+    /// Numbers tried in a cell.
+    pub placements: usize,
+    /// Cells where every possible number led to a dead-end.
+    pub backtracks: usize,
+    /// Most guesses stacked up at once, the depth `solve_with_stack_limit` limits.
+    pub max_depth: usize,
+}
+
+/// How far `solve_rec` can go before giving up.
+#[derive(Clone, Copy)]
+struct SearchLimits {
+    /// Cells the search can visit, see `solve_with_budget`.
+    max_nodes: usize,
+    /// Guesses that can be stacked up at once, see `solve_with_stack_limit`.
+    max_depth: usize,
+}
+
+impl SearchLimits {
+    const NONE: SearchLimits = SearchLimits {
+        max_nodes: usize::MAX,
+        max_depth: usize::MAX,
+    };
+
+    /// Whether the search has gone past either limit. Both counters only ever go up, so once this
+    /// is true it stays true and the search stops altogether rather than trying the next branch.
+    fn exhausted(&self, counters: &PerfCounters) -> bool {
+        counters.nodes > self.max_nodes || counters.max_depth > self.max_depth
+    }
+}
+
+impl FromStr for Sudoku {
+    type Err = ParseSudokuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn parse_row(line: &str, side: u8) -> Result<Vec<Option<u8>>, ParseSudokuError> {
+            line.chars().map(|c| Sudoku::parse_cell(c, side)).collect()
+        }
+
+        // Blank lines and `#` comments are only there for people reading the file.
+        let lines = s
+            .lines()
+            .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .collect::<Vec<&str>>();
+
+        // A single line is all 81 cells in a row, the way most puzzle databases give them.
+        if let [line] = lines[..] {
+            return Sudoku::from_line(&line.split_whitespace().collect::<String>());
+        }
+
+        // The size comes from the first row, 4, 9, or 16 cells.
+        let side = lines.first().map_or(0, |line| line.chars().count());
+        if !BOX_SIZES
+            .iter()
+            .any(|&box_size| (box_size * box_size) as usize == side)
+        {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        let grid = lines
+            .iter()
+            .map(|line| parse_row(line, side as u8))
+            .collect::<Result<Vec<Vec<Option<u8>>>, Self::Err>>()?;
+
+        if grid.len() != side {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        if let Some((row, cells)) = grid
+            .iter()
+            .enumerate()
+            .find(|(_, cells)| cells.len() != side)
+        {
+            return Err(ParseSudokuError::InvalidRowLength {
+                row,
+                len: cells.len(),
+            });
+        }
+
+        Ok(Sudoku::new(grid))
+    }
+}
+
+impl Display for Sudoku {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // `{:#}` draws the houses with borders, for reading rather than parsing.
+        if f.alternate() {
+            let box_size = self.box_size as usize;
+            let border = format!("+{}", format!("{}+", "-".repeat(box_size)).repeat(box_size));
+
+            for (i, row) in self.grid.iter().enumerate() {
+                if i % box_size == 0 {
+                    writeln!(f, "{border}")?;
+                }
+                for (j, &cell) in row.iter().enumerate() {
+                    if j % box_size == 0 {
+                        write!(f, "|")?;
+                    }
+                    write!(f, "{}", Sudoku::cell_char(cell))?;
+                }
+                writeln!(f, "|")?;
+            }
+
+            return writeln!(f, "{border}");
+        }
+
+        for row in &self.grid {
+            for &cell in row {
+                write!(f, "{}", Sudoku::cell_char(cell))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Index<Coord> for Sudoku {
+    type Output = Option<u8>;
+
+    fn index(&self, coord: Coord) -> &Self::Output {
+        &self.grid[coord.row as usize][coord.col as usize]
+    }
+}
+
+impl IndexMut<Coord> for Sudoku {
+    fn index_mut(&mut self, coord: Coord) -> &mut Self::Output {
+        &mut self.grid[coord.row as usize][coord.col as usize]
+    }
+}
+
+impl Sudoku {
+    /// Number of cells on each side of a standard grid, also the largest number.
+    pub const SIZE: u8 = 9;
+    /// Width (and height) of a house in a standard grid.
+    pub const BOX_SIZE: u8 = 3;
+    /// Number of cells in a standard grid.
+    pub const CELL_COUNT: usize = 81;
+
+    /// Builds a sudoku from rows of cells with no restrictions, without checking anything. The box
+    /// size comes from the number of rows.
+    pub(crate) fn new(grid: Vec<Vec<Option<u8>>>) -> Self {
+        let side = grid.len();
+        let box_size = (1..)
+            .find(|&size| size * size >= side)
+            .unwrap_or(Sudoku::BOX_SIZE as usize);
+        Sudoku {
+            grid,
+            box_size: box_size as u8,
+            restrictions: vec![vec![Sudoku::all_numbers(side as u8); side]; side],
+            adjacency: Vec::new(),
+            diagonals: false,
+            cages: Vec::new(),
+        }
+    }
+
+    /// Gets the width (and height) of a house, 3 for a standard grid.
+    pub fn box_size(&self) -> u8 {
+        self.box_size
+    }
+
+    /// Gets the number of cells on each side of the grid, also the largest number, 9 for a standard
+    /// grid.
+    pub fn side(&self) -> u8 {
+        self.box_size * self.box_size
+    }
+
+    /// Gets every coord of the grid in row-major order.
+    pub(crate) fn coords(&self) -> impl Iterator<Item = Coord> {
+        let side = self.side();
+        (0..side).flat_map(move |row| (0..side).map(move |col| Coord { row, col }))
+    }
+
+    /// Gets every number 1 to `side` as a bitmask, bit n for number n.
+    pub(crate) fn all_numbers(side: u8) -> u32 {
+        ((1 << (side + 1)) - 1) & !1
+    }
+
+    /// Parses a single cell, `.` or `0` for an empty cell or a number up to `side`: 1-9, then `a`-`g`
+    /// for 10-16.
+    pub(crate) fn parse_cell(c: char, side: u8) -> Result<Option<u8>, ParseSudokuError> {
+        let n = match c {
+            '.' | '0' => return Ok(None),
+            '1'..='9' => c as u8 - b'0',
+            'a'..='g' => c as u8 - b'a' + 10,
+            _ => return Err(ParseSudokuError::InvalidDigit(c)),
+        };
+
+        if n > side {
+            return Err(ParseSudokuError::InvalidDigit(c));
+        }
+
+        Ok(Some(n))
+    }
+
+    /// Gets the character for a cell, the reverse of `parse_cell`.
+    pub(crate) fn cell_char(cell: Option<u8>) -> char {
+        match cell {
+            None => '.',
+            Some(n @ 1..=9) => (b'0' + n) as char,
+            Some(n) => (b'a' + n - 10) as char,
+        }
+    }
+
+    /// Restricts the cell to only the given numbers, on top of any restriction it already has. The
+    /// number in a restricted cell has to be one of them for the grid to be valid. Numbers outside 1
+    /// to the side length are ignored, so allowing only those leaves the cell with no numbers at all.
+    pub fn restrict(&mut self, coord: Coord, allowed: impl IntoIterator<Item = u8>) {
+        let side = self.side();
+        let mask = allowed
+            .into_iter()
+            .filter(|n| (1..=side).contains(n))
+            .fold(0, |mask, n| mask | 1 << n);
+        self.restrictions[coord.row as usize][coord.col as usize] &= mask;
+    }
+
+    /// Builds a sudoku from rows of cells, checking it's square (4x4, 9x9, or 16x16) and every
+    /// filled cell is 1 to the side length.
+    pub fn from_grid(grid: Vec<Vec<Option<u8>>>) -> Result<Self, ParseSudokuError> {
+        let side = grid.len();
+        let square = BOX_SIZES
+            .iter()
+            .any(|&box_size| (box_size * box_size) as usize == side);
+        if !square || grid.iter().any(|row| row.len() != side) {
+            return Err(ParseSudokuError::InvalidSize);
+        }
+
+        if let Some(n) = grid
+            .iter()
+            .flatten()
+            .flatten()
+            .find(|n| !(1..=side as u8).contains(*n))
+        {
+            return Err(ParseSudokuError::InvalidValue(*n));
+        }
+
+        Ok(Sudoku::new(grid))
+    }
+
+    /// Builds a standard sudoku from an array of rows, checking every filled cell is 1 to 9
+    /// (`InvalidCell` otherwise) and no given is repeated in a row, col, or house (`DuplicateGiven`).
+    pub fn from_array(grid: [[Option<u8>; 9]; 9]) -> Result<Self, InvalidSudokuError> {
+        let sudoku = Sudoku::new(grid.iter().map(|row| row.to_vec()).collect());
+
+        if let Some(coord) = sudoku
+            .coords()
+            .find(|&coord| sudoku.get(coord).is_some_and(|n| !(1..=9).contains(&n)))
+        {
+            return Err(InvalidSudokuError::InvalidCell(coord));
+        }
+
+        if sudoku.has_duplicates() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        Ok(sudoku)
+    }
+
+    /// Gets the cells of a standard grid as an array of rows, the reverse of `from_array`. Other
+    /// sizes don't fit the array, so they get `None`.
+    pub fn to_array(&self) -> Option<[[Option<u8>; 9]; 9]> {
+        (self.side() == Sudoku::SIZE)
+            .then(|| std::array::from_fn(|row| std::array::from_fn(|col| self.grid[row][col])))
+    }
+
+    /// Returns a solved sudoku based on the current state, or an error indicating unsolvable.
+    /// An empty grid always solves to the same base pattern, see `base_pattern`. Anything forced is
+    /// filled in first with `propagate`, then the rest is backtracked, propagating after each guess.
+    pub fn solve(&self) -> Result<Self, InvalidSudokuError> {
+        self.solve_with_budget(usize::MAX)
+    }
+
+    /// Same as `solve`, but gives up with `BudgetExhausted` once the search has visited more than
+    /// `max_nodes` cells. Givens that already clash are `DuplicateGiven`, before any searching, with
+    /// `check_valid` or `conflicts` saying where. Consistent givens with no solution are `Unsolvable`.
+    pub fn solve_with_budget(&self, max_nodes: usize) -> Result<Self, InvalidSudokuError> {
+        let limits = SearchLimits {
+            max_nodes,
+            ..SearchLimits::NONE
+        };
+        Ok(self.solve_with_counters(limits)?.0)
+    }
+
+    /// Same as `solve`, but gives up with `StackLimitExceeded` once more than `max_depth` guesses are
+    /// stacked up at once, bounding the memory the search takes. A guess can't stack up without an
+    /// empty cell to put it in, so no limit at or above the number of empty cells is ever hit.
+    pub fn solve_with_stack_limit(&self, max_depth: usize) -> Result<Self, InvalidSudokuError> {
+        let limits = SearchLimits {
+            max_depth,
+            ..SearchLimits::NONE
+        };
+        Ok(self.solve_with_counters(limits)?.0)
+    }
+
+    /// Same as `solve`, also returning counters of the work the search did. Unlike timing, they're
+    /// the same every run, so they make for stable performance assertions.
+    pub fn solve_counted(&self) -> Result<(Self, PerfCounters), InvalidSudokuError> {
+        self.solve_with_counters(SearchLimits::NONE)
+    }
+
+    /// Same as `solve`, but fills in this grid rather than a copy of it. If there's no solution, the
+    /// grid is left as it was.
+    pub fn solve_mut(&mut self) -> Result<(), InvalidSudokuError> {
+        self.solve_mut_with_counters(SearchLimits::NONE)?;
+        Ok(())
+    }
+
+    fn solve_with_counters(
+        &self,
+        limits: SearchLimits,
+    ) -> Result<(Self, PerfCounters), InvalidSudokuError> {
+        let mut sudoku = self.clone();
+        let counters = sudoku.solve_mut_with_counters(limits)?;
+        Ok((sudoku, counters))
+    }
+
+    fn solve_mut_with_counters(
+        &mut self,
+        limits: SearchLimits,
+    ) -> Result<PerfCounters, InvalidSudokuError> {
+        if self.check_valid().is_err() {
+            return Err(InvalidSudokuError::DuplicateGiven);
+        }
+
+        // Restrictions could rule the base pattern out, so only take the shortcut without any.
+        let any_number = Sudoku::all_numbers(self.side());
+        let unrestricted = self
+            .restrictions
+            .iter()
+            .flatten()
+            .all(|&mask| mask == any_number);
+        let variant = !self.adjacency.is_empty() || self.diagonals || !self.cages.is_empty();
+        if self.clue_count() == 0 && unrestricted && !variant {
+            self.grid = Sudoku::base_pattern(self.box_size).grid;
+            return Ok(PerfCounters::default());
+        }
+
+        #[cfg(debug_assertions)]
+        let puzzle = self.clone();
+        // Only these get filled, so emptying them again undoes everything.
+        let empty = self
+            .coords()
+            .filter(|&coord| self.get(coord).is_none())
+            .collect::<Vec<Coord>>();
+
+        let result = if self.enforce_arc_consistency() {
+            self.search(limits)
+        } else {
+            Err(InvalidSudokuError::Unsolvable)
+        };
+        if result.is_err() {
+            for coord in empty {
+                self.unset(coord);
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        assert!(puzzle.solution_preserves_givens(self));
+        result
+    }
+
+    /// Fills in everything forced, then backtracks through the rest within the limits.
+    fn search(&mut self, limits: SearchLimits) -> Result<PerfCounters, InvalidSudokuError> {
+        self.propagate()?;
+
+        let mut counters = PerfCounters::default();
+        self.solve_rec(&mut counters, limits, 0);
+
+        if counters.nodes > limits.max_nodes {
+            return Err(InvalidSudokuError::BudgetExhausted);
+        }
+        if counters.max_depth > limits.max_depth {
+            return Err(InvalidSudokuError::StackLimitExceeded);
+        }
+
+        match self.validate() {
+            Ok(_) => Ok(counters),
+            Err(_) => Err(InvalidSudokuError::Unsolvable),
+        }
+    }
+
+    /// Returns whether every filled cell of this puzzle is unchanged in the solution. Empty cells
+    /// don't matter, so it also checks a player's progress agrees with the solution so far.
+    pub fn solution_preserves_givens(&self, solution: &Sudoku) -> bool {
+        self.grid
+            .iter()
+            .flatten()
+            .zip(solution.grid.iter().flatten())
+            .all(|(given, solved)| given.is_none() || given == solved)
+    }
+
+    /// Returns whether the solution agrees with this puzzle, keeping every number it was given.
+    /// The same check as `solution_preserves_givens`, read from the puzzle's side.
+    pub fn agrees_with(&self, solution: &Sudoku) -> bool {
+        self.solution_preserves_givens(solution)
+    }
+
+    fn solve_rec(
+        &mut self,
+        counters: &mut PerfCounters,
+        limits: SearchLimits,
+        depth: usize,
+    ) -> bool {
+        // The method of this is to try each of the possible numbers and continue on.
+        // If there are no possible numbers, then we've hit a dead-end and return up the stack.
+
+        // Out of budget or too many guesses deep, unwind all the way up.
+        counters.nodes += 1;
+        counters.max_depth = counters.max_depth.max(depth);
+        if limits.exhausted(counters) {
+            return false;
+        }
+
+        // Always fill the most constrained cell next, it keeps the search tree small.
+        counters.candidate_evaluations += self.empty_cells();
+        let Some(coord) = self.most_constrained_cell() else {
+            // No empty cells left, every placement was possible so it's solved.
+            return true;
+        };
+
+        // Loop through each of the possible numbers, trying it and continuing to the next cell.
+        for n in Sudoku::mask_numbers(self.possible_mask(coord)) {
+            self.set(coord, n);
+            counters.placements += 1;
+
+            // Fill in whatever the guess forces, then carry on unless it's already a dead-end.
+            let forced = self.fill_singles();
+
+            // If this is returning true, that means we found our solution, keep returning up.
+            if !self.has_dead_end() && self.solve_rec(counters, limits, depth + 1) {
+                return true;
+            }
+            if limits.exhausted(counters) {
+                return false;
+            }
+
+            for (coord, _) in forced {
+                self.unset(coord);
+            }
+        }
+
+        // Solution not found, unset this cell and return false,
+        // trying another possible number further up the chain.
+        self.unset(coord);
+        counters.backtracks += 1;
+        false
+    }
+
+    /// Gets a full, valid grid without searching. Each row is the one above shifted by the box size,
+    /// or by one more at the start of each band, giving 123456789 / 456789123 / 789123456 / 234567891...
+    pub(crate) fn base_pattern(box_size: u8) -> Self {
+        let (box_size, side) = (box_size as usize, (box_size * box_size) as usize);
+        Sudoku::new(
+            (0..side)
+                .map(|row| {
+                    (0..side)
+                        .map(|col| Some(((row * box_size + row / box_size + col) % side + 1) as u8))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Gets the number of filled cells.
+    pub fn clue_count(&self) -> usize {
+        self.grid
+            .iter()
+            .flatten()
+            .filter(|cell| cell.is_some())
+            .count()
+    }
+
+    /// Gets the number of unfilled cells.
+    pub fn empty_cells(&self) -> usize {
+        self.coords().count() - self.clue_count()
+    }
+
+    /// Returns whether every cell is filled. It says nothing about whether the grid is valid, see
+    /// `validate` for that.
+    pub fn is_complete(&self) -> bool {
+        self.grid.iter().flatten().all(Option::is_some)
+    }
+
+    /// Validates the current state of the sudoku.
+    pub fn validate(&self) -> Result<(), HashSet<InvalidSudokuError>> {
+        let mut errors = HashSet::new();
+        let (box_size, side) = (self.box_size, self.side());
+        let total = side as u32 * (side as u32 + 1) / 2;
+        let sum = |numbers: HashSet<u8>| numbers.into_iter().map(u32::from).sum::<u32>();
+
+        for n in 0..side {
+            if sum(self.get_row(n)) != total {
+                errors.insert(InvalidSudokuError::InvalidRow(n));
+            }
+
+            if sum(self.get_col(n)) != total {
+                errors.insert(InvalidSudokuError::InvalidCol(n));
+            }
+
+            let house_coord = Coord {
+                row: n / box_size,
+                col: n % box_size,
+            };
+            if sum(self.get_house(house_coord)) != total {
+                errors.insert(InvalidSudokuError::InvalidHouse(house_coord));
+            }
+
+            for col in 0..side {
+                let coord = Coord { row: n, col };
+                let allowed = self.restrictions[n as usize][col as usize];
+                if self.get(coord).is_some_and(|n| allowed & (1 << n) == 0) {
+                    errors.insert(InvalidSudokuError::InvalidCell(coord));
+                }
+            }
+        }
+
+        if let Err(adjacency_errors) = self.validate_adjacency() {
+            errors.extend(adjacency_errors);
+        }
+        self.check_diagonals(&mut errors, true);
+        self.check_cages(&mut errors, true);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+
+    /// Validates each region of a jigsaw sudoku, where `regions` gives the region (0 to one less
+    /// than the side length) that each cell belongs to, row by row. Only the regions are checked,
+    /// not rows or cols.
+    pub fn validate_regions<R: AsRef<[u8]>>(
+        &self,
+        regions: &[R],
+    ) -> Result<(), HashSet<InvalidSudokuError>> {
+        let mut errors = HashSet::new();
+        let side = self.side();
+        let total = side as u32 * (side as u32 + 1) / 2;
+
+        for region in 0..side {
+            let cells = self
+                .coords()
+                .filter(|coord| {
+                    regions
+                        .get(coord.row as usize)
+                        .and_then(|row| row.as_ref().get(coord.col as usize))
+                        == Some(&region)
+                })
+                .map(|coord| self.get(coord))
+                .collect::<Vec<Option<u8>>>();
+            let numbers = cells.iter().filter_map(|&n| n).collect::<HashSet<u8>>();
+
+            if cells.len() != side as usize
+                || numbers.into_iter().map(u32::from).sum::<u32>() != total
+            {
+                errors.insert(InvalidSudokuError::InvalidRegion(region));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+
+    /// Checks a partially filled grid for any number placed more than once in a row, col, or house.
+    /// Unlike `validate`, empty cells are fine.
+    pub fn check_valid(&self) -> Result<(), HashSet<InvalidSudokuError>> {
+        let mut errors = HashSet::new();
+        let unique = |cells: Vec<Option<u8>>| {
+            let placed = cells.into_iter().flatten().collect::<Vec<u8>>();
+            placed.iter().collect::<HashSet<&u8>>().len() == placed.len()
+        };
+
+        let box_size = self.box_size;
+        for n in 0..self.side() {
+            if !unique(self.grid[n as usize].clone()) {
+                errors.insert(InvalidSudokuError::InvalidRow(n));
+            }
+
+            if !unique(self.grid.iter().map(|row| row[n as usize]).collect()) {
+                errors.insert(InvalidSudokuError::InvalidCol(n));
+            }
+
+            let house_coord = Coord {
+                row: n / box_size,
+                col: n % box_size,
+            };
+            let house = (0..self.side())
+                .map(|i| {
+                    self.get(Coord {
+                        row: house_coord.row * box_size + i / box_size,
+                        col: house_coord.col * box_size + i % box_size,
+                    })
+                })
+                .collect();
+            if !unique(house) {
+                errors.insert(InvalidSudokuError::InvalidHouse(house_coord));
+            }
+        }
+        self.check_diagonals(&mut errors, false);
+        self.check_cages(&mut errors, false);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+
+    /// Gets every filled cell that shares its number with another cell in its row, col, or house,
+    /// the cells to highlight when `check_valid` fails. Empty cells are never conflicts.
+    pub fn conflicts(&self) -> HashSet<Coord> {
+        let mut conflicts = HashSet::new();
+
+        for unit in self.units() {
+            for &coord in &unit {
+                let n = self.get(coord);
+                if n.is_some()
+                    && unit
+                        .iter()
+                        .any(|&other| other != coord && self.get(other) == n)
+                {
+                    conflicts.insert(coord);
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Returns whether any number is already in a row, col, or house more than once.
+    pub(crate) fn has_duplicates(&self) -> bool {
+        self.check_valid().is_err()
+    }
+
+    /// Gets the filled cells that don't match the solution. Empty cells aren't mistakes.
+    pub fn mistakes(&self, solution: &Sudoku) -> Vec<Coord> {
+        self.coords()
+            .filter(|&coord| {
+                self.get(coord)
+                    .is_some_and(|n| solution.get(coord) != Some(n))
+            })
+            .collect()
+    }
+
+    /// Gets how many more of each number (1 to the side length, by index) need placing to complete
+    /// the grid. Cells holding something outside 1 to the side length, which `set` doesn't stop,
+    /// aren't any number's and are skipped.
+    pub fn remaining_digit_counts(&self) -> Vec<u8> {
+        let side = self.side();
+        let mut counts = vec![side; side as usize];
+        for &n in self.grid.iter().flatten().flatten() {
+            if let Some(count) = (n as usize).checked_sub(1).and_then(|i| counts.get_mut(i)) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        counts
+    }
+
+    /// Gets the cell at the coord
+    pub fn get(&self, coord: Coord) -> Option<u8> {
+        self.grid[coord.row as usize][coord.col as usize]
+    }
+
+    /// Sets the cell to Some(value)
+    pub fn set(&mut self, coord: Coord, value: u8) {
+        self.grid[coord.row as usize][coord.col as usize] = Some(value);
+    }
+
+    /// Sets the cell to Some(value), checking it first. A number outside 1 to the side length, or
+    /// one ruled out by the cell's restriction or a variant rule, is `InvalidCell`. A number already
+    /// elsewhere in the cell's row, col, or house is `InvalidRow`, `InvalidCol`, or `InvalidHouse`.
+    /// The grid is left as it was on an error.
+    pub fn try_set(&mut self, coord: Coord, value: u8) -> Result<(), InvalidSudokuError> {
+        if !(1..=self.side()).contains(&value) {
+            return Err(InvalidSudokuError::InvalidCell(coord));
+        }
+
+        // The cell's own number doesn't count against it.
+        let previous = self.get(coord);
+        self.unset(coord);
+
+        let house = coord.house_in(self.box_size);
+        let result = if self.get_row(coord.row).contains(&value) {
+            Err(InvalidSudokuError::InvalidRow(coord.row))
+        } else if self.get_col(coord.col).contains(&value) {
+            Err(InvalidSudokuError::InvalidCol(coord.col))
+        } else if self.get_house(house).contains(&value) {
+            Err(InvalidSudokuError::InvalidHouse(house))
+        } else if self.possible_mask(coord) & (1 << value) == 0 {
+            Err(InvalidSudokuError::InvalidCell(coord))
+        } else {
+            Ok(())
+        };
+
+        match result {
+            Ok(()) => self.set(coord, value),
+            Err(_) => self.grid[coord.row as usize][coord.col as usize] = previous,
+        }
+        result
+    }
+
+    /// Sets the cell to None
+    pub fn unset(&mut self, coord: Coord) {
+        self.grid[coord.row as usize][coord.col as usize] = None;
+    }
+
+    /// Clears everything but the givens, the cells filled in `givens`, which go back to their
+    /// given numbers. Handy for starting a puzzle over without keeping a copy of it around.
+    pub fn retain_givens(&mut self, givens: &Sudoku) {
+        for (cell, given) in self
+            .grid
+            .iter_mut()
+            .flatten()
+            .zip(givens.grid.iter().flatten())
+        {
+            *cell = *given;
+        }
+    }
+
+    /// Gets the empty cell with the fewest possible numbers, or None if the grid is full.
+    pub(crate) fn most_constrained_cell(&self) -> Option<Coord> {
+        let mut best: Option<(Coord, u32)> = None;
+
+        for coord in self.coords() {
+            if self.get(coord).is_some() {
+                continue;
+            }
+
+            let count = self.possible_mask(coord).count_ones();
+            if best.is_none_or(|(_, best_count)| count < best_count) {
+                best = Some((coord, count));
+            }
+        }
+
+        best.map(|(coord, _)| coord)
+    }
+
+    /// Returns whether any empty cell has no possible numbers left.
+    pub(crate) fn has_dead_end(&self) -> bool {
+        self.coords()
+            .any(|coord| self.get(coord).is_none() && self.possible_mask(coord) == 0)
+    }
+
+    /// Gets all possible numbers at the given coordinate. A filled cell has none.
+    pub fn get_possible_numbers(&self, coord: Coord) -> HashSet<u8> {
+        self.get_sorted_possible_numbers(coord)
+            .into_iter()
+            .collect()
+    }
+
+    /// Gets all possible numbers at the given coordinate in ascending order, so searches try them
+    /// in the same order every run. A filled cell has none.
+    pub fn get_sorted_possible_numbers(&self, coord: Coord) -> Vec<u8> {
+        if self.get(coord).is_some() {
+            return Vec::new();
+        }
+
+        Sudoku::mask_numbers(self.possible_mask(coord)).collect()
+    }
+
+    /// Gets the possible numbers at the given coordinate as a bitmask, bit n set for each possible
+    /// number n. This is what the solver uses internally, it doesn't allocate.
+    pub(crate) fn possible_mask(&self, coord: Coord) -> u32 {
+        let (row, col) = (coord.row as usize, coord.col as usize);
+        let box_size = self.box_size as usize;
+        let (row_start, col_start) = (row / box_size * box_size, col / box_size * box_size);
+
+        let mut used = 0;
+        for i in 0..self.grid.len() {
+            for n in [
+                self.grid[row][i],
+                self.grid[i][col],
+                self.grid[row_start + i / box_size][col_start + i % box_size],
+            ]
+            .into_iter()
+            .flatten()
+            {
+                used |= 1 << n;
+            }
+        }
+
+        !used
+            & self.restrictions[row][col]
+            & self.adjacency_mask(coord)
+            & self.diagonal_mask(coord)
+            & self.cage_mask(coord)
+    }
+
+    /// Gets the numbers in a bitmask from `possible_mask`, in ascending order.
+    pub(crate) fn mask_numbers(mut mask: u32) -> impl Iterator<Item = u8> {
+        std::iter::from_fn(move || {
+            if mask == 0 {
+                return None;
+            }
+
+            let n = mask.trailing_zeros() as u8;
+            mask &= mask - 1;
+            Some(n)
+        })
+    }
+
+    /// Gets all present numbers in a row.
+    pub fn get_row(&self, index: u8) -> HashSet<u8> {
+        self.grid[index as usize]
+            .iter()
+            .filter_map(|&n| n)
+            .collect()
+    }
+
+    /// Gets all present numbers in a col.
+    pub fn get_col(&self, index: u8) -> HashSet<u8> {
+        self.grid
+            .iter()
+            .filter_map(|row| row[index as usize])
+            .collect()
+    }
+
+    /// Gets all present numbers in the house at coord. Note this is a house coordinate,
+    /// So Coord { row: 2, col: 1 } would return the bottom-middle house.
+    pub fn get_house(&self, coord: Coord) -> HashSet<u8> {
+        let mut house = HashSet::new();
+
+        let box_size = self.box_size;
+        let row_start = coord.row * box_size;
+        let row_end = coord.row * box_size + box_size;
+        let col_start = coord.col * box_size;
+        let col_end = coord.col * box_size + box_size;
+
+        for row in row_start..row_end {
+            for col in col_start..col_end {
+                if let Some(n) = self.grid[row as usize][col as usize] {
+                    house.insert(n);
+                }
+            }
+        }
+
+        house
+    }
+
+    /// Gets all present numbers in the cell's row, col, and house combined. Without restrictions or
+    /// adjacency rules, an empty cell's possible numbers are exactly the ones not in here.
+    pub fn used_around(&self, coord: Coord) -> HashSet<u8> {
+        let house = coord.house_in(self.box_size);
+        &(&self.get_row(coord.row) | &self.get_col(coord.col)) | &self.get_house(house)
+    }
+
+    /// Gets the incomplete unit with the fewest empty cells, along with how many it has, the place
+    /// to look to finish something quickly. The first of any tie (rows, then cols, then houses) wins.
+    /// A full grid has no incomplete units.
+    pub fn most_complete_unit(&self) -> Option<(Unit, usize)> {
+        self.units()
+            .iter()
+            .map(|cells| {
+                cells
+                    .iter()
+                    .filter(|&&coord| self.get(coord).is_none())
+                    .count()
+            })
+            .enumerate()
+            .filter(|&(_, empty)| empty > 0)
+            .min_by_key(|&(index, empty)| (empty, index))
+            .map(|(index, empty)| (Unit::from_index(index, self.box_size), empty))
+    }
+
+    /// Gets the coords of every unit: the rows, then the cols, then the houses.
+    pub(crate) fn units(&self) -> Vec<Vec<Coord>> {
+        let (box_size, side) = (self.box_size, self.side());
+        let rows = (0..side).map(|row| (0..side).map(|col| Coord { row, col }).collect());
+        let cols = (0..side).map(|col| (0..side).map(|row| Coord { row, col }).collect());
+        let houses = (0..side).map(|house| {
+            (0..side)
+                .map(|i| Coord {
+                    row: house / box_size * box_size + i / box_size,
+                    col: house % box_size * box_size + i % box_size,
+                })
+                .collect()
+        });
+
+        rows.chain(cols).chain(houses).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_easy() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let result = sudoku.solve().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_from_str_single_line() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let line = sudoku.to_line();
+        assert_eq!(81, line.len());
+        assert_eq!(sudoku, line.parse::<Sudoku>().unwrap());
+        assert_eq!(sudoku, format!("  {line}\n").parse::<Sudoku>().unwrap());
+
+        assert!(matches!(
+            line[..80].parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidSize)
+        ));
+        assert!(matches!(
+            format!("{line}.").parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn test_from_str_comments() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let rows = sudoku.rows_as_strings();
+        let spaced = format!(
+            "# The easy puzzle\n{}\n\n{}\n  \n{}\n# done\n",
+            rows[..3].join("\n"),
+            rows[3..6].join("\n"),
+            rows[6..].join("\n")
+        );
+        assert_eq!(sudoku, spaced.parse::<Sudoku>().unwrap());
+
+        let line = format!("# One line\n\n{}\n", rows.concat());
+        assert_eq!(sudoku, line.parse::<Sudoku>().unwrap());
+
+        // Taking out the comments still has to leave nine rows.
+        let short = format!(
+            "# Missing a row\n{}\n\n{}",
+            rows[..4].join("\n"),
+            rows[5..].join("\n")
+        );
+        assert!(matches!(
+            short.parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidSize)
+        ));
+        assert!(matches!(
+            "# Nothing\n\n".parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidSize)
+        ));
+    }
+
+    #[test]
+    fn test_from_str_zero_blanks() {
+        let dots = include_str!("easy.txt");
+        let zeros = dots.replace('.', "0");
+        assert_eq!(
+            dots.parse::<Sudoku>().unwrap(),
+            zeros.parse::<Sudoku>().unwrap()
+        );
+
+        let stray = dots.replacen('.', "x", 1);
+        assert!(matches!(
+            stray.parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidDigit('x'))
+        ));
+    }
+
+    #[test]
+    fn test_from_str_ragged() {
+        let mut lines = include_str!("easy.txt").lines().collect::<Vec<&str>>();
+        lines[4] = &lines[4][..8];
+        assert!(matches!(
+            lines.join("\n").parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidRowLength { row: 4, len: 8 })
+        ));
+
+        let long = format!("{}.", lines[0]);
+        lines[4] = &long;
+        assert!(matches!(
+            lines.join("\n").parse::<Sudoku>(),
+            Err(ParseSudokuError::InvalidRowLength { row: 4, len: 10 })
+        ));
+    }
+
+    #[test]
+    fn test_display_alternate() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let bordered = format!("{sudoku:#}");
+        let lines = bordered.lines().collect::<Vec<&str>>();
+        assert_eq!(13, lines.len());
+        assert_eq!("+---+---+---+", lines[0]);
+        assert_eq!("|.9.|...|..6|", lines[1]);
+        assert_eq!("+---+---+---+", lines[12]);
+
+        // The plain output is unchanged and still parses.
+        assert_eq!(sudoku, sudoku.to_string().parse::<Sudoku>().unwrap());
+        assert!(!sudoku.to_string().contains('|'));
+    }
+
+    #[test]
+    fn test_get_house() {
+        fn house(input: &str) -> HashSet<u8> {
+            input
+                .chars()
+                .map(|c| c.to_string().parse().unwrap())
+                .collect()
+        }
+
+        let sudoku = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let result = sudoku.get_house(Coord { row: 0, col: 0 });
+        assert_eq!(house("894235167"), result);
+        let result = sudoku.get_house(Coord { row: 0, col: 1 });
+        assert_eq!(house("137468592"), result);
+        let result = sudoku.get_house(Coord { row: 2, col: 2 });
+        assert_eq!(house("947153682"), result);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut sudoku = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.validate().is_ok());
+
+        sudoku.grid[4][6] = Some(9);
+        let expected: HashSet<InvalidSudokuError> = vec![
+            InvalidSudokuError::InvalidRow(4),
+            InvalidSudokuError::InvalidCol(6),
+            InvalidSudokuError::InvalidHouse(Coord { row: 1, col: 2 }),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected, sudoku.validate().unwrap_err())
+    }
+
+    #[test]
+    fn test_get_possible_numbers() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let result = sudoku.get_possible_numbers(Coord { row: 0, col: 0 });
+        let expected: HashSet<u8> = vec![1, 2, 3, 4, 8].into_iter().collect();
+        assert_eq!(expected, result);
+        let result = sudoku.get_possible_numbers(Coord { row: 8, col: 0 });
+        let expected: HashSet<u8> = vec![3, 4].into_iter().collect();
+        assert_eq!(expected, result);
+        // Filled, so there's nothing possible.
+        let result = sudoku.get_possible_numbers(Coord { row: 8, col: 8 });
+        assert!(result.is_empty());
+        assert!(sudoku
+            .get_sorted_possible_numbers(Coord { row: 8, col: 8 })
+            .is_empty());
+    }
+
+    #[test]
+    fn test_try_set() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let coord = Coord { row: 0, col: 0 };
+        assert_eq!(Ok(()), sudoku.try_set(coord, 8));
+        assert_eq!(Some(8), sudoku.get(coord));
+        // Setting the same number again is fine.
+        assert_eq!(Ok(()), sudoku.try_set(coord, 8));
+
+        let before = sudoku.clone();
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidCell(coord)),
+            sudoku.try_set(coord, 42)
+        );
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidCell(coord)),
+            sudoku.try_set(coord, 0)
+        );
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidRow(0)),
+            sudoku.try_set(coord, 9)
+        );
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidCol(0)),
+            sudoku.try_set(coord, 5)
+        );
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidHouse(Coord { row: 0, col: 1 })),
+            sudoku.try_set(Coord { row: 0, col: 4 }, 2)
+        );
+        assert_eq!(before, sudoku);
+
+        sudoku.restrict(coord, [1, 2]);
+        assert_eq!(
+            Err(InvalidSudokuError::InvalidCell(coord)),
+            sudoku.try_set(coord, 3)
+        );
+        assert_eq!(Ok(()), sudoku.try_set(coord, 2));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let (given, empty) = (Coord { row: 0, col: 1 }, Coord { row: 0, col: 0 });
+        assert_eq!(Some(9), sudoku[given]);
+        assert_eq!(sudoku.get(empty), sudoku[empty]);
+
+        sudoku[empty] = Some(8);
+        assert_eq!(Some(8), sudoku.get(empty));
+        sudoku.set(empty, 4);
+        assert_eq!(Some(4), sudoku[empty]);
+        sudoku[given] = None;
+        assert_eq!(None, sudoku.get(given));
+    }
+
+    #[test]
+    fn test_used_around() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let coord = Coord { row: 0, col: 0 };
+        let used = sudoku.used_around(coord);
+        let possible = sudoku.get_possible_numbers(coord);
+
+        assert!(used.is_disjoint(&possible));
+        assert_eq!((1..=9).collect::<HashSet<u8>>(), &used | &possible);
+    }
+
+    #[test]
+    fn test_solve_empty() {
+        let empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        assert_eq!(0, empty.clue_count());
+
+        // It's the base pattern without any searching at all.
+        let (result, counters) = empty.solve_counted().unwrap();
+        assert_eq!(PerfCounters::default(), counters);
+
+        assert!(result.validate().is_ok());
+        assert_eq!(Sudoku::base_pattern(3), result);
+        assert_eq!(81, result.clue_count());
+    }
+
+    #[test]
+    fn test_solve_counted() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let (solution, counters) = sudoku.solve_counted().unwrap();
+        assert_eq!(expected, solution);
+
+        // Propagation does it all without any guessing.
+        let expected = PerfCounters {
+            nodes: 1,
+            candidate_evaluations: 0,
+            placements: 0,
+            backtracks: 0,
+            max_depth: 0,
+        };
+        assert_eq!(expected, counters);
+
+        let hard = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = PerfCounters {
+            nodes: 15,
+            candidate_evaluations: 587,
+            placements: 22,
+            backtracks: 4,
+            max_depth: 10,
+        };
+        assert_eq!(expected, hard.solve_counted().unwrap().1);
+
+        // Naked singles alone finish it, so there's nothing to backtrack.
+        let singles = include_str!("singles.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(0, singles.solve_counted().unwrap().1.backtracks);
+    }
+
+    #[test]
+    fn test_solve_seventeen() {
+        // A 17-clue puzzle, the fewest a unique puzzle can have.
+        let sudoku = include_str!("seventeen.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(17, sudoku.clue_count());
+
+        let (result, counters) = sudoku.solve_counted().unwrap();
+        assert!(result.validate().is_ok());
+        assert!(sudoku.solution_preserves_givens(&result));
+
+        // Despite so few clues, propagation gets all the way without a single guess.
+        assert_eq!(1, counters.nodes);
+        assert_eq!(0, counters.placements);
+    }
+
+    #[test]
+    fn test_solve_escargot() {
+        let sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("escargot_solved.txt")
+            .parse::<Sudoku>()
+            .unwrap();
+
+        let (result, counters) = sudoku.solve_counted().unwrap();
+        assert_eq!(expected, result);
+
+        // Made to be hard for people, but a handful of guesses is enough for the search.
+        assert!(counters.nodes <= 20);
+        assert!(counters.backtracks <= 5);
+    }
+
+    #[test]
+    fn test_from_grid() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(sudoku, Sudoku::from_grid(sudoku.grid.clone()).unwrap());
+
+        let mut ragged = sudoku.grid.clone();
+        ragged[3].pop();
+        assert!(matches!(
+            Sudoku::from_grid(ragged),
+            Err(ParseSudokuError::InvalidSize)
+        ));
+
+        let mut out_of_range = sudoku.grid.clone();
+        out_of_range[0][0] = Some(10);
+        assert!(matches!(
+            Sudoku::from_grid(out_of_range),
+            Err(ParseSudokuError::InvalidValue(10))
+        ));
+    }
+
+    #[test]
+    fn test_from_array() {
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let grid = easy.to_array().unwrap();
+        let top = [None, Some(9), None, None, None, None, None, None, Some(6)];
+        assert_eq!(top, grid[0]);
+
+        let sudoku = Sudoku::from_array(grid).unwrap();
+        assert_eq!(easy, sudoku);
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(solved.to_array(), sudoku.solve().unwrap().to_array());
+        assert_eq!(None, Sudoku::base_pattern(2).to_array());
+        assert_eq!(None, Sudoku::base_pattern(4).to_array());
+
+        let mut out_of_range = grid;
+        out_of_range[4][2] = Some(10);
+        assert_eq!(
+            InvalidSudokuError::InvalidCell(Coord { row: 4, col: 2 }),
+            Sudoku::from_array(out_of_range).unwrap_err()
+        );
+
+        let mut duplicate = grid;
+        duplicate[0][0] = Some(9);
+        assert_eq!(
+            InvalidSudokuError::DuplicateGiven,
+            Sudoku::from_array(duplicate).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_regions() {
+        let sudoku = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        // Every row is a region.
+        let mut regions: [[u8; 9]; 9] = std::array::from_fn(|row| [row as u8; 9]);
+        assert!(sudoku.validate_regions(&regions).is_ok());
+
+        // Swap the first cells of the first two regions, giving both a duplicate.
+        regions[0][0] = 1;
+        regions[1][0] = 0;
+        let expected: HashSet<InvalidSudokuError> = vec![
+            InvalidSudokuError::InvalidRegion(0),
+            InvalidSudokuError::InvalidRegion(1),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(expected, sudoku.validate_regions(&regions).unwrap_err());
+
+        // Rows as regions work the same on the other sizes.
+        for box_size in [2, 4] {
+            let solved = Sudoku::base_pattern(box_size);
+            let side = solved.side();
+            let mut regions = (0..side)
+                .map(|row| vec![row; side as usize])
+                .collect::<Vec<_>>();
+            assert!(solved.validate_regions(&regions).is_ok());
+
+            regions[0][0] = 1;
+            regions[1][0] = 0;
+            assert_eq!(2, solved.validate_regions(&regions).unwrap_err().len());
+        }
+    }
+
+    #[test]
+    fn test_solve_deterministic() {
+        // Plenty of solutions, so the one found depends on the order numbers are tried.
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        sudoku.unset(Coord { row: 1, col: 4 });
+        sudoku.unset(Coord { row: 1, col: 5 });
+        assert!(sudoku.count_solutions(2) > 1);
+
+        let first = sudoku.solve().unwrap();
+        for _ in 0..50 {
+            assert_eq!(first, sudoku.solve().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_solve_with_budget() {
+        // AI Escargot, notorious for being slow to brute force.
+        let sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("escargot_solved.txt")
+            .parse::<Sudoku>()
+            .unwrap();
+        assert_eq!(expected, sudoku.solve_with_budget(100_000).unwrap());
+
+        assert_eq!(
+            InvalidSudokuError::BudgetExhausted,
+            sudoku.solve_with_budget(10).unwrap_err()
+        );
+
+        // A near-empty grid skips the empty grid shortcut, so it has to search too.
+        let mut sparse = Sudoku::new(vec![vec![None; 9]; 9]);
+        sparse.set(Coord { row: 4, col: 4 }, 5);
+        assert_eq!(
+            InvalidSudokuError::BudgetExhausted,
+            sparse.solve_with_budget(10).unwrap_err()
+        );
+        assert!(sparse.solve_with_budget(1_000).is_ok());
+    }
+
+    #[test]
+    fn test_solve_mut() {
+        let mut sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("escargot_solved.txt")
+            .parse::<Sudoku>()
+            .unwrap();
+        sudoku.solve_mut().unwrap();
+        assert_eq!(expected, sudoku);
+
+        // Propagation fills plenty in before finding there's no 8 left for the top-left cell.
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let mut unsolvable = easy.clone();
+        unsolvable.restrict(Coord { row: 0, col: 0 }, (1..=9).filter(|&n| n != 8));
+        let before = unsolvable.clone();
+        assert_eq!(
+            InvalidSudokuError::Unsolvable,
+            unsolvable.solve_mut().unwrap_err()
+        );
+        assert_eq!(before, unsolvable);
+
+        let mut empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        empty.solve_mut().unwrap();
+        assert_eq!(Sudoku::base_pattern(3), empty);
+    }
+
+    #[test]
+    fn test_solve_with_stack_limit() {
+        let sudoku = include_str!("escargot.txt").parse::<Sudoku>().unwrap();
+        let expected = include_str!("escargot_solved.txt")
+            .parse::<Sudoku>()
+            .unwrap();
+        assert_eq!(
+            expected,
+            sudoku.solve_with_stack_limit(sudoku.empty_cells()).unwrap()
+        );
+
+        assert_eq!(
+            InvalidSudokuError::StackLimitExceeded,
+            sudoku.solve_with_stack_limit(1).unwrap_err()
+        );
+
+        // It needs 10 guesses deep, and stops the moment it goes past 5, rather than going on to try
+        // the other numbers on the way down. So it only visited one node at each depth.
+        let mut limited = sudoku.clone();
+        limited.propagate().unwrap();
+        let mut counters = PerfCounters::default();
+        let limits = SearchLimits {
+            max_depth: 5,
+            ..SearchLimits::NONE
+        };
+        assert!(!limited.solve_rec(&mut counters, limits, 0));
+        assert_eq!(6, counters.max_depth);
+        assert_eq!(7, counters.nodes);
+        assert_eq!(
+            InvalidSudokuError::StackLimitExceeded,
+            sudoku.solve_with_stack_limit(5).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_solve_rec_dead_end() {
+        // A full grid is already solved.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let mut sudoku = solved.clone();
+        assert!(sudoku.solve_rec(&mut PerfCounters::default(), SearchLimits::NONE, 0));
+        assert_eq!(solved, sudoku);
+
+        // With a wrong 2 in front of it, the last cell has nothing left (its 8 is already
+        // in its column) and has to report the dead-end.
+        let mut sudoku = solved.clone();
+        sudoku.set(Coord { row: 8, col: 7 }, 2);
+        sudoku.unset(Coord { row: 8, col: 8 });
+        assert!(!sudoku.solve_rec(&mut PerfCounters::default(), SearchLimits::NONE, 0));
+        assert_eq!(None, sudoku.get(Coord { row: 8, col: 8 }));
+    }
+
+    #[test]
+    fn test_restrict() {
+        // The bottom-left 7s and 4s can swap with the 7 and 4 further along, two solutions.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let mut sudoku = solved.clone();
+        for (row, col) in [(7, 0), (7, 4), (8, 0), (8, 4)] {
+            sudoku.unset(Coord { row, col });
+        }
+        assert_eq!(2, sudoku.count_solutions(2));
+
+        let mut odd = sudoku.clone();
+        odd.restrict(Coord { row: 7, col: 0 }, [1, 3, 5, 7, 9]);
+        assert_eq!(1, odd.count_solutions(2));
+        let result = odd.solve().unwrap();
+        assert_eq!(Some(7), result.get(Coord { row: 7, col: 0 }));
+        assert_eq!(solved.grid, result.grid);
+
+        let mut even = sudoku.clone();
+        even.restrict(Coord { row: 7, col: 0 }, [2, 4, 6, 8]);
+        let result = even.solve().unwrap();
+        assert_eq!(Some(4), result.get(Coord { row: 7, col: 0 }));
+        assert_eq!(Some(7), result.get(Coord { row: 7, col: 4 }));
+
+        // A filled cell breaking its restriction isn't valid.
+        let mut broken = solved.clone();
+        broken.restrict(Coord { row: 7, col: 0 }, [2, 4, 6, 8]);
+        let expected = vec![InvalidSudokuError::InvalidCell(Coord { row: 7, col: 0 })]
+            .into_iter()
+            .collect();
+        assert_eq!(Err(expected), broken.validate());
+
+        // Out of range numbers don't count, whether 0, past the side, or past the mask's bits.
+        let coord = Coord { row: 7, col: 0 };
+        let mut out_of_range = sudoku.clone();
+        out_of_range.restrict(coord, [0, 7, 10, 40, 255]);
+        assert_eq!(vec![7], out_of_range.get_sorted_possible_numbers(coord));
+        assert_eq!(solved.grid, out_of_range.solve().unwrap().grid);
+
+        let mut nothing = sudoku.clone();
+        nothing.restrict(coord, [40]);
+        assert!(nothing.get_possible_numbers(coord).is_empty());
+        assert!(nothing.solve().is_err());
+    }
+
+    #[test]
+    fn test_most_complete_unit() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(None, solved.most_complete_unit());
+
+        // The top row is missing three, but each of their cols is only missing one.
+        let mut sudoku = solved.clone();
+        for col in [0, 3, 6] {
+            sudoku.unset(Coord { row: 0, col });
+        }
+        assert_eq!(Some((Unit::Col(0), 1)), sudoku.most_complete_unit());
+
+        // Now the second row only missing one comes first, ahead of the cols.
+        sudoku.unset(Coord { row: 1, col: 0 });
+        assert_eq!(Some((Unit::Row(1), 1)), sudoku.most_complete_unit());
+    }
+
+    #[test]
+    fn test_check_valid() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(Ok(()), sudoku.check_valid());
+
+        // A second 9 in the top row, the top-left house, and col 2.
+        sudoku.set(Coord { row: 0, col: 2 }, 9);
+        let expected = vec![
+            InvalidSudokuError::InvalidRow(0),
+            InvalidSudokuError::InvalidCol(2),
+            InvalidSudokuError::InvalidHouse(Coord { row: 0, col: 0 }),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(Err(expected), sudoku.check_valid());
+        assert_eq!(
+            InvalidSudokuError::DuplicateGiven,
+            sudoku.solve().unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_solve_unsolvable() {
+        // Nothing clashes, but the 9 below the end of the top row leaves nothing for it.
+        let mut consistent = Sudoku::new(vec![vec![None; 9]; 9]);
+        for col in 0..8 {
+            consistent.set(Coord { row: 0, col }, col + 1);
+        }
+        consistent.set(Coord { row: 5, col: 8 }, 9);
+        assert_eq!(Ok(()), consistent.check_valid());
+        assert_eq!(
+            InvalidSudokuError::Unsolvable,
+            consistent.solve().unwrap_err()
+        );
+
+        // Putting a 9 at the end of the top row clashes with it instead.
+        let mut contradictory = consistent.clone();
+        contradictory.set(Coord { row: 0, col: 8 }, 9);
+        assert_eq!(
+            InvalidSudokuError::DuplicateGiven,
+            contradictory.solve().unwrap_err()
+        );
+        let expected = HashSet::from([Coord { row: 0, col: 8 }, Coord { row: 5, col: 8 }]);
+        assert_eq!(expected, contradictory.conflicts());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.conflicts().is_empty());
+
+        // A second 9 in the top row.
+        sudoku.set(Coord { row: 0, col: 4 }, 9);
+        let expected = HashSet::from([Coord { row: 0, col: 1 }, Coord { row: 0, col: 4 }]);
+        assert_eq!(expected, sudoku.conflicts());
+
+        // The 6 clashes with one in its house, one further down its col, and one at the end of its
+        // row.
+        sudoku.set(Coord { row: 0, col: 3 }, 6);
+        let expected = HashSet::from([
+            Coord { row: 0, col: 1 },
+            Coord { row: 0, col: 4 },
+            Coord { row: 0, col: 3 },
+            Coord { row: 0, col: 8 },
+            Coord { row: 1, col: 4 },
+            Coord { row: 3, col: 3 },
+        ]);
+        assert_eq!(expected, sudoku.conflicts());
+    }
+
+    #[test]
+    fn test_solution_preserves_givens() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let mut solution = sudoku.solve().unwrap();
+        assert!(sudoku.solution_preserves_givens(&solution));
+
+        // A player's correct progress agrees with it, blanks and all.
+        let mut progress = sudoku.clone();
+        progress.set(Coord { row: 0, col: 0 }, 8);
+        progress.set(Coord { row: 2, col: 0 }, 1);
+        assert!(progress.solution_preserves_givens(&solution));
+
+        progress.set(Coord { row: 8, col: 0 }, 3);
+        assert!(!progress.solution_preserves_givens(&solution));
+
+        solution.set(Coord { row: 0, col: 1 }, 1);
+        assert!(!sudoku.solution_preserves_givens(&solution));
+        assert!(!sudoku.agrees_with(&solution));
+        assert!(sudoku.agrees_with(&sudoku.solve().unwrap()));
+    }
+
+    #[test]
+    fn test_retain_givens() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let mut solution = sudoku.solve().unwrap();
+        solution.retain_givens(&sudoku);
+        assert_eq!(sudoku, solution);
+
+        // A player's wrong entries go too, and givens they overwrote come back.
+        let mut progress = sudoku.clone();
+        progress.set(Coord { row: 0, col: 0 }, 3);
+        progress.set(Coord { row: 0, col: 1 }, 1);
+        progress.retain_givens(&sudoku);
+        assert_eq!(sudoku, progress);
+    }
+
+    #[test]
+    fn test_mistakes() {
+        let mut sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let solution = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert!(sudoku.mistakes(&solution).is_empty());
+
+        sudoku.set(Coord { row: 0, col: 0 }, 8);
+        sudoku.set(Coord { row: 2, col: 0 }, 2);
+        sudoku.set(Coord { row: 8, col: 8 }, 1);
+        assert_eq!(
+            vec![Coord { row: 2, col: 0 }, Coord { row: 8, col: 8 }],
+            sudoku.mistakes(&solution)
+        );
+    }
+
+    #[test]
+    fn test_remaining_digit_counts() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(
+            vec![5, 4, 7, 4, 5, 3, 5, 6, 6],
+            sudoku.remaining_digit_counts()
+        );
+
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(vec![0; 9], solved.remaining_digit_counts());
+
+        let four = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+        assert_eq!(vec![3, 3, 3, 3], four.remaining_digit_counts());
+
+        let mut out_of_range = four.clone();
+        out_of_range.set(Coord { row: 0, col: 1 }, 0);
+        out_of_range.set(Coord { row: 0, col: 2 }, 5);
+        assert_eq!(vec![3, 3, 3, 3], out_of_range.remaining_digit_counts());
+
+        let mut sixteen = Sudoku::base_pattern(4);
+        sixteen.unset(Coord { row: 0, col: 15 });
+        let counts = sixteen.remaining_digit_counts();
+        assert_eq!(16, counts.len());
+        assert_eq!(1, counts[15]);
+        assert_eq!(1, counts.iter().sum::<u8>());
+    }
+
+    #[test]
+    fn test_empty_cells_and_is_complete() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(45, sudoku.empty_cells());
+        assert!(!sudoku.is_complete());
+
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(0, solved.empty_cells());
+        assert!(solved.is_complete());
+    }
+
+    #[test]
+    fn test_solve_four_by_four() {
+        let sudoku = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+        assert_eq!(2, sudoku.box_size());
+        let result = sudoku.solve().unwrap();
+        assert!(result.is_complete());
+        assert!(result.validate().is_ok());
+        assert_eq!(Some(1), result.get(Coord { row: 0, col: 0 }));
+        assert_eq!(Some(2), result.get(Coord { row: 3, col: 3 }));
+    }
+
+    #[test]
+    fn test_solve_sixteen_by_sixteen() {
+        let mut sudoku = Sudoku::base_pattern(4);
+        for (i, coord) in sudoku.coords().collect::<Vec<_>>().into_iter().enumerate() {
+            if i % 3 != 0 {
+                sudoku.unset(coord);
+            }
+        }
+
+        let text = sudoku.to_string();
+        assert!(text.contains('g'));
+        assert_eq!(sudoku, text.parse::<Sudoku>().unwrap());
+
+        let result = sudoku.solve().unwrap();
+        assert!(result.validate().is_ok());
+        for coord in sudoku.coords() {
+            if let Some(n) = sudoku.get(coord) {
+                assert_eq!(Some(n), result.get(coord));
+            }
+        }
+    }
+}
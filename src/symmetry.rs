@@ -0,0 +1,540 @@
+use std::collections::HashSet;
+
+use crate::{Coord, InvalidSudokuError, Sudoku};
+
+/// Gets every ordering of 0 to `n - 1`, in lexicographic order.
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![Vec::new()];
+    }
+
+    (0..n)
+        .flat_map(|first| {
+            permutations(n - 1).into_iter().map(move |rest| {
+                let mut perm = vec![first];
+                perm.extend(rest.into_iter().map(|i| if i >= first { i + 1 } else { i }));
+                perm
+            })
+        })
+        .collect()
+}
+
+/// Gets every way of rearranging the rows (or cols) of a grid with the given box size while keeping
+/// the bands intact. Line `i` of the result comes from line `map[i]`.
+fn line_maps(box_size: usize) -> Vec<Vec<usize>> {
+    let perms = permutations(box_size);
+
+    // Every choice of order for the lines within each band, one band at a time.
+    let mut inners: Vec<Vec<&Vec<usize>>> = vec![Vec::new()];
+    for _ in 0..box_size {
+        inners = inners
+            .into_iter()
+            .flat_map(|inner| {
+                perms.iter().map(move |perm| {
+                    let mut inner = inner.clone();
+                    inner.push(perm);
+                    inner
+                })
+            })
+            .collect();
+    }
+
+    perms
+        .iter()
+        .flat_map(|outer| {
+            inners.iter().map(move |inner| {
+                (0..box_size * box_size)
+                    .map(|i| outer[i / box_size] * box_size + inner[i / box_size][i % box_size])
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+impl Sudoku {
+    /// Gets the coords of each horizontal band (a row of houses), top to bottom.
+    pub fn bands(&self) -> Vec<Vec<Coord>> {
+        let (box_size, side) = (self.box_size, self.side());
+        (0..box_size)
+            .map(|band| {
+                (band * box_size..(band + 1) * box_size)
+                    .flat_map(|row| (0..side).map(move |col| Coord { row, col }))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Gets the coords of each vertical stack (a col of houses), left to right.
+    pub fn stacks(&self) -> Vec<Vec<Coord>> {
+        let (box_size, side) = (self.box_size, self.side());
+        (0..box_size)
+            .map(|stack| {
+                (0..side)
+                    .flat_map(|row| {
+                        (stack * box_size..(stack + 1) * box_size)
+                            .map(move |col| Coord { row, col })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Rearranges the bands, band `i` of the result is band `order[i]` of this grid.
+    /// `order` must be a permutation of 0 to one less than the box size.
+    pub fn permute_bands(&self, order: &[usize]) -> Self {
+        let box_size = self.box_size as usize;
+        debug_assert_eq!(box_size, order.len());
+        self.remap(|row, col| (order[row / box_size] * box_size + row % box_size, col))
+    }
+
+    /// Rearranges the stacks, stack `i` of the result is stack `order[i]` of this grid.
+    /// `order` must be a permutation of 0 to one less than the box size.
+    pub fn permute_stacks(&self, order: &[usize]) -> Self {
+        let box_size = self.box_size as usize;
+        debug_assert_eq!(box_size, order.len());
+        self.remap(|row, col| (row, order[col / box_size] * box_size + col % box_size))
+    }
+
+    /// Rearranges the rows within a band, row `i` of the band in the result is row `order[i]` of
+    /// the band in this grid. `order` must be a permutation of 0 to one less than the box size.
+    pub fn permute_band_rows(&self, band: usize, order: &[usize]) -> Self {
+        let box_size = self.box_size as usize;
+        debug_assert_eq!(box_size, order.len());
+        self.remap(|row, col| {
+            if row / box_size == band {
+                (band * box_size + order[row % box_size], col)
+            } else {
+                (row, col)
+            }
+        })
+    }
+
+    /// Rearranges the cols within a stack, col `i` of the stack in the result is col `order[i]`
+    /// of the stack in this grid. `order` must be a permutation of 0 to one less than the box size.
+    pub fn permute_stack_cols(&self, stack: usize, order: &[usize]) -> Self {
+        let box_size = self.box_size as usize;
+        debug_assert_eq!(box_size, order.len());
+        self.remap(|row, col| {
+            if col / box_size == stack {
+                (row, stack * box_size + order[col % box_size])
+            } else {
+                (row, col)
+            }
+        })
+    }
+
+    /// Builds a new grid where each cell (and its restriction) is taken from the (row, col) of this
+    /// grid given by `source`.
+    fn remap(&self, source: impl Fn(usize, usize) -> (usize, usize)) -> Self {
+        let side = self.side() as usize;
+        let mut sudoku = Sudoku::new(vec![vec![None; side]; side]);
+        for row in 0..side {
+            for col in 0..side {
+                let (from_row, from_col) = source(row, col);
+                sudoku.grid[row][col] = self.grid[from_row][from_col];
+                sudoku.restrictions[row][col] = self.restrictions[from_row][from_col];
+            }
+        }
+        sudoku
+    }
+
+    /// Gets how many distinct grids this one can be turned into with validity-preserving symmetry:
+    /// transposing, permuting bands, stacks, rows within a band, cols within a stack, and relabeling
+    /// numbers. Symmetries of the grid itself shrink the orbit, so it's found from the size of the
+    /// whole group divided by the number of symmetries that map the grid onto itself. A standard
+    /// grid's group has over a trillion symmetries, more than a 32-bit `usize` holds, so it's
+    /// counted in `u64`. A 16x16 grid's group is too big to search or even count that way, so it
+    /// gets `None`.
+    pub fn orbit_size(&self) -> Option<u64> {
+        fn factorial(n: u64) -> u64 {
+            (1..=n).product()
+        }
+
+        if self.box_size > Sudoku::BOX_SIZE {
+            return None;
+        }
+
+        let side = self.side() as usize;
+        let line_maps = line_maps(self.box_size as usize);
+
+        // Numbers that don't appear can be relabeled freely, each mapping is that many symmetries.
+        let used = self
+            .grid
+            .iter()
+            .flatten()
+            .flatten()
+            .collect::<HashSet<&u8>>()
+            .len() as u64;
+        let free_relabels = factorial(side as u64 - used);
+
+        // Transforms are bijections, so if every filled cell comes from a filled cell the empty
+        // cells line up too, and only the filled cells need checking.
+        let filled = (0..side)
+            .flat_map(|row| (0..side).map(move |col| (row, col)))
+            .filter_map(|(row, col)| self.grid[row][col].map(|n| (row, col, n)))
+            .collect::<Vec<(usize, usize, u8)>>();
+
+        // Labels are indexed by number, so sized for a standard grid, the largest one searched.
+        let mut automorphisms = 0;
+        for transpose in [false, true] {
+            for rows in &line_maps {
+                for cols in &line_maps {
+                    let mut labels = [0u8; 10];
+                    let mut reverse = [0u8; 10];
+
+                    let consistent = filled.iter().all(|&(row, col, to)| {
+                        let (src_row, src_col) = if transpose {
+                            (cols[col], rows[row])
+                        } else {
+                            (rows[row], cols[col])
+                        };
+
+                        let Some(from) = self.grid[src_row][src_col] else {
+                            return false;
+                        };
+
+                        let (from, to) = (from as usize, to as usize);
+                        if labels[from] == 0 && reverse[to] == 0 {
+                            labels[from] = to as u8;
+                            reverse[to] = from as u8;
+                        }
+                        labels[from] as usize == to && reverse[to] as usize == from
+                    });
+
+                    if consistent {
+                        automorphisms += free_relabels;
+                    }
+                }
+            }
+        }
+
+        let line_maps_len = line_maps.len() as u64;
+        let group_size = 2 * line_maps_len * line_maps_len * factorial(side as u64);
+        Some(group_size / automorphisms)
+    }
+
+    /// Solves the sudoku and gets the lexicographically smallest grid (row by row) in its solution's
+    /// orbit, so any two solutions that are the same up to symmetry give the same canonical grid.
+    /// A 16x16 grid has too many symmetries to search, so it gets `GridTooLarge`.
+    pub fn canonical_solution(&self) -> Result<Sudoku, InvalidSudokuError> {
+        if self.box_size > Sudoku::BOX_SIZE {
+            return Err(InvalidSudokuError::GridTooLarge);
+        }
+
+        let solution = self.solve()?;
+        let (box_size, side) = (self.box_size as usize, self.side() as usize);
+        let cells = solution
+            .grid
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.unwrap_or(0)).collect())
+            .collect::<Vec<Vec<u8>>>();
+        let transposed = (0..side)
+            .map(|row| (0..side).map(|col| cells[col][row]).collect())
+            .collect::<Vec<Vec<u8>>>();
+
+        // Relabeling always turns the top row into 1 to `side` in order, which then fixes the
+        // labels. So only the source of the top row and the col order need searching, the
+        // remaining rows can just be sorted: within each band, and the other bands by their first
+        // row.
+        let mut best: Option<Vec<Vec<u8>>> = None;
+        for grid in [&cells, &transposed] {
+            for cols in line_maps(box_size) {
+                for top in 0..side {
+                    let mut labels = [0u8; 10];
+                    for (i, &col) in cols.iter().enumerate() {
+                        labels[grid[top][col] as usize] = i as u8 + 1;
+                    }
+                    let relabel = |row: usize| -> Vec<u8> {
+                        cols.iter()
+                            .map(|&col| labels[grid[row][col] as usize])
+                            .collect()
+                    };
+
+                    let band = top / box_size;
+                    let mut rest = (band * box_size..(band + 1) * box_size)
+                        .filter(|&row| row != top)
+                        .map(relabel)
+                        .collect::<Vec<Vec<u8>>>();
+                    rest.sort();
+
+                    let mut other_bands = (0..box_size)
+                        .filter(|&other| other != band)
+                        .map(|other| {
+                            let mut rows = (other * box_size..(other + 1) * box_size)
+                                .map(relabel)
+                                .collect::<Vec<Vec<u8>>>();
+                            rows.sort();
+                            rows
+                        })
+                        .collect::<Vec<Vec<Vec<u8>>>>();
+                    other_bands.sort();
+
+                    let mut rows = vec![relabel(top)];
+                    rows.extend(rest);
+                    rows.extend(other_bands.into_iter().flatten());
+
+                    if best.as_ref().is_none_or(|best| rows < *best) {
+                        best = Some(rows);
+                    }
+                }
+            }
+        }
+
+        Ok(Sudoku::new(
+            best.unwrap_or_default()
+                .into_iter()
+                .map(|row| row.into_iter().map(Some).collect())
+                .collect(),
+        ))
+    }
+
+    /// Returns whether every cell and its 180 degree rotation sum to one more than the side, 10 on
+    /// a standard grid, a curious class of solution grids. Only complete grids can have this
+    /// symmetry.
+    pub fn has_value_symmetry(&self) -> bool {
+        let side = self.side() as usize;
+        (0..side).all(|row| {
+            (0..side).all(|col| {
+                match (
+                    self.grid[row][col],
+                    self.grid[side - 1 - row][side - 1 - col],
+                ) {
+                    (Some(a), Some(b)) => (a + b) as usize == side + 1,
+                    _ => false,
+                }
+            })
+        })
+    }
+
+    /// Gets which cells hold givens, ignoring their numbers, as a bitmask of cols for each row. Two
+    /// puzzles with the same pattern share a clue layout.
+    pub fn givens_pattern(&self) -> Vec<u16> {
+        self.grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, cell)| cell.is_some())
+                    .fold(0, |pattern, (col, _)| pattern | 1 << col)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bands_and_stacks() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+
+        for (i, band) in sudoku.bands().iter().enumerate() {
+            let rows = band.iter().map(|coord| coord.row).collect::<HashSet<u8>>();
+            let expected = (i as u8 * 3..i as u8 * 3 + 3).collect::<HashSet<u8>>();
+            assert_eq!(expected, rows);
+            assert_eq!(27, band.iter().collect::<HashSet<&Coord>>().len());
+        }
+
+        for (i, stack) in sudoku.stacks().iter().enumerate() {
+            let cols = stack.iter().map(|coord| coord.col).collect::<HashSet<u8>>();
+            let expected = (i as u8 * 3..i as u8 * 3 + 3).collect::<HashSet<u8>>();
+            assert_eq!(expected, cols);
+            assert_eq!(27, stack.iter().collect::<HashSet<&Coord>>().len());
+        }
+
+        // Other sizes have as many bands and stacks as their box size, all inside the grid.
+        for box_size in [2, 4] {
+            let sudoku = Sudoku::base_pattern(box_size);
+            let side = sudoku.side();
+            for lines in [sudoku.bands(), sudoku.stacks()] {
+                assert_eq!(box_size as usize, lines.len());
+                let coords = lines.iter().flatten().collect::<HashSet<&Coord>>();
+                assert_eq!(side as usize * side as usize, coords.len());
+                assert!(coords
+                    .iter()
+                    .all(|coord| coord.row < side && coord.col < side));
+            }
+            assert_eq!(
+                (box_size..box_size * 2).collect::<HashSet<u8>>(),
+                sudoku.stacks()[1].iter().map(|coord| coord.col).collect()
+            );
+        }
+    }
+
+    #[test]
+    fn test_permute_bands_and_stacks() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+
+        let result = solved.permute_bands(&[2, 0, 1]);
+        assert!(result.validate().is_ok());
+        assert_eq!(solved.grid[6], result.grid[0]);
+        assert_eq!(solved.grid[0], result.grid[3]);
+
+        let result = solved.permute_stacks(&[1, 2, 0]);
+        assert!(result.validate().is_ok());
+        assert_eq!(solved.grid[0][3], result.grid[0][0]);
+
+        let result = solved.permute_band_rows(1, &[2, 1, 0]);
+        assert!(result.validate().is_ok());
+        assert_eq!(solved.grid[5], result.grid[3]);
+        assert_eq!(solved.grid[0], result.grid[0]);
+
+        let result = solved.permute_stack_cols(0, &[1, 0, 2]);
+        assert!(result.validate().is_ok());
+        assert_eq!(solved.grid[4][1], result.grid[4][0]);
+
+        // Empty cells move along with everything else.
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let result = sudoku.permute_bands(&[1, 2, 0]).permute_stacks(&[2, 0, 1]);
+        assert_eq!(
+            sudoku
+                .grid
+                .iter()
+                .flatten()
+                .filter(|cell| cell.is_none())
+                .count(),
+            result
+                .grid
+                .iter()
+                .flatten()
+                .filter(|cell| cell.is_none())
+                .count()
+        );
+
+        for box_size in [2, 4] {
+            let solved = Sudoku::base_pattern(box_size);
+            let last = box_size as usize - 1;
+            let order = (0..box_size as usize).rev().collect::<Vec<usize>>();
+            let side = solved.side() as usize;
+
+            let result = solved.permute_bands(&order);
+            assert!(result.validate().is_ok());
+            assert_eq!(side, result.grid.len());
+            assert_eq!(solved.grid[last * box_size as usize], result.grid[0]);
+
+            let result = solved
+                .permute_stacks(&order)
+                .permute_band_rows(last, &order);
+            assert!(result.validate().is_ok());
+            assert_eq!(solved.grid[0][last * box_size as usize], result.grid[0][0]);
+
+            let result = solved.permute_stack_cols(0, &order);
+            assert!(result.validate().is_ok());
+            assert_eq!(solved.grid[0][last], result.grid[0][0]);
+        }
+    }
+
+    #[test]
+    fn test_orbit_size() {
+        // A single clue in the middle can be moved to any of the 81 cells, as any of the 9 numbers.
+        let mut symmetric = Sudoku::new(vec![vec![None; 9]; 9]);
+        symmetric.set(Coord { row: 4, col: 4 }, 5);
+        assert_eq!(Some(729), symmetric.orbit_size());
+
+        let asymmetric = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(asymmetric.orbit_size() > symmetric.orbit_size());
+
+        // Nothing to tell grids apart, so every symmetry maps the empty grid onto itself.
+        assert_eq!(Some(1), Sudoku::new(vec![vec![None; 9]; 9]).orbit_size());
+
+        // The same goes for the 16 cells and 4 numbers of a 4x4 grid.
+        let mut four = Sudoku::new(vec![vec![None; 4]; 4]);
+        four.set(Coord { row: 1, col: 2 }, 3);
+        assert_eq!(Some(64), four.orbit_size());
+
+        assert_eq!(None, Sudoku::base_pattern(4).orbit_size());
+    }
+
+    #[test]
+    fn test_canonical_solution() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let canonical = sudoku.canonical_solution().unwrap();
+        assert!(canonical.validate().is_ok());
+        assert_eq!("123456789", canonical.to_string().lines().next().unwrap());
+
+        // A quarter turn is a transpose and a flip, so it lands on the same canonical grid.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let rotated = solved.remap(|row, col| (8 - col, row));
+        assert_ne!(solved, rotated);
+        assert_eq!(canonical, rotated.canonical_solution().unwrap());
+
+        // An unrelated solution grid.
+        let other = include_str!("input.txt").parse::<Sudoku>().unwrap();
+        assert_ne!(canonical, other.canonical_solution().unwrap());
+
+        // 4x4 grids work the same way, with two bands and stacks to move around.
+        let four = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+        let canonical = four.canonical_solution().unwrap();
+        assert!(canonical.validate().is_ok());
+        assert_eq!("1234", canonical.to_string().lines().next().unwrap());
+        let moved = four
+            .solve()
+            .unwrap()
+            .permute_bands(&[1, 0])
+            .permute_stack_cols(1, &[1, 0]);
+        assert_eq!(canonical, moved.canonical_solution().unwrap());
+
+        assert_eq!(
+            Err(InvalidSudokuError::GridTooLarge),
+            Sudoku::base_pattern(4).canonical_solution()
+        );
+    }
+
+    #[test]
+    fn test_has_value_symmetry() {
+        // In the base pattern each cell's rotation holds the number matching it under this relabel.
+        let relabel = [1, 2, 3, 4, 6, 7, 8, 9, 5];
+        let symmetric = Sudoku::new(
+            Sudoku::base_pattern(3)
+                .grid
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|n| n.map(|n| relabel[n as usize - 1]))
+                        .collect()
+                })
+                .collect(),
+        );
+        assert!(symmetric.validate().is_ok());
+        assert!(symmetric.has_value_symmetry());
+
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert!(!solved.has_value_symmetry());
+
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        assert!(!sudoku.has_value_symmetry());
+
+        // On a 4x4 grid the pairs sum to 5.
+        let four = "1243\n3421\n4312\n2134".parse::<Sudoku>().unwrap();
+        assert!(four.validate().is_ok());
+        assert!(four.has_value_symmetry());
+        assert!(!Sudoku::base_pattern(2).has_value_symmetry());
+        assert!(!Sudoku::base_pattern(4).has_value_symmetry());
+    }
+
+    #[test]
+    fn test_givens_pattern() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let pattern = sudoku.givens_pattern();
+        // .9......6
+        assert_eq!(1 << 1 | 1 << 8, pattern[0]);
+
+        let mut relabeled = sudoku.clone();
+        for row in &mut relabeled.grid {
+            for n in row.iter_mut().flatten() {
+                *n = *n % 9 + 1;
+            }
+        }
+        assert_ne!(sudoku, relabeled);
+        assert_eq!(pattern, relabeled.givens_pattern());
+
+        let mut fewer = sudoku.clone();
+        fewer.unset(Coord { row: 0, col: 1 });
+        assert_ne!(pattern, fewer.givens_pattern());
+
+        let four = "1...\n..3.\n.4..\n...2".parse::<Sudoku>().unwrap();
+        assert_eq!(vec![1, 1 << 2, 1 << 1, 1 << 3], four.givens_pattern());
+        assert_eq!(vec![u16::MAX; 16], Sudoku::base_pattern(4).givens_pattern());
+    }
+}
@@ -0,0 +1,46 @@
+use crate::Coord;
+
+/// A row, col, or house of the grid. Houses are given by house coordinate like `get_house`, so
+/// `House(Coord { row: 2, col: 1 })` is the bottom-middle house.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Unit {
+    Row(u8),
+    Col(u8),
+    House(Coord),
+}
+
+impl Unit {
+    /// Gets the unit at the given index of `Sudoku::units` for a grid with the given box size:
+    /// rows, then cols, then houses.
+    pub(crate) fn from_index(index: usize, box_size: u8) -> Self {
+        let side = (box_size * box_size) as usize;
+        let n = (index % side) as u8;
+        match index / side {
+            0 => Unit::Row(n),
+            1 => Unit::Col(n),
+            _ => Unit::House(Coord {
+                row: n / box_size,
+                col: n % box_size,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sudoku;
+
+    #[test]
+    fn test_from_index() {
+        let sudoku = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        for (index, cells) in sudoku.units().into_iter().enumerate() {
+            let matches = |coord: &Coord| match Unit::from_index(index, 3) {
+                Unit::Row(row) => coord.row == row,
+                Unit::Col(col) => coord.col == col,
+                Unit::House(house) => coord.house() == house,
+            };
+            assert!(cells.iter().all(matches));
+        }
+    }
+}
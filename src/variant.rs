@@ -0,0 +1,600 @@
+use std::collections::HashSet;
+
+use crate::{ConstraintError, Coord, InvalidSudokuError, Sudoku};
+
+/// A rule between two orthogonally adjacent cells, for consecutive style puzzles.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Adjacency {
+    /// The two numbers differ by one, a white dot.
+    Consecutive,
+    /// The two numbers don't differ by one.
+    NotConsecutive,
+}
+
+impl Adjacency {
+    /// Gets the numbers next to `n` is allowed to be under this rule, as a bitmask like
+    /// `possible_mask`.
+    fn allowed_mask(self, n: u8, side: u8) -> u32 {
+        let consecutive = (1 << (n + 1) | 1 << (n - 1)) & Sudoku::all_numbers(side);
+        match self {
+            Adjacency::Consecutive => consecutive,
+            Adjacency::NotConsecutive => !consecutive,
+        }
+    }
+}
+
+/// A killer sudoku cage, cells that add up to `sum` without repeating a number.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Cage {
+    pub cells: Vec<Coord>,
+    pub sum: u8,
+}
+
+impl Cage {
+    /// Gets the cells' numbers, leaving out empty cells.
+    fn numbers<'a>(&'a self, sudoku: &'a Sudoku) -> impl Iterator<Item = u8> + 'a {
+        self.cells.iter().filter_map(|&coord| sudoku.get(coord))
+    }
+}
+
+/// Extra rules to check or solve a grid under without attaching them for good, see
+/// `validate_for_variant`. They go on top of the standard rules and any already attached.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct Variant {
+    /// Both main diagonals also need every number once, like `with_diagonals`.
+    pub diagonals: bool,
+    /// Killer cages, like `add_cage`.
+    pub cages: Vec<Cage>,
+}
+
+impl Sudoku {
+    /// Attaches the variant's rules, on top of any the grid already has. Added cages are numbered
+    /// after the existing ones.
+    pub fn with_variant(mut self, variant: &Variant) -> Self {
+        self.diagonals |= variant.diagonals;
+        self.cages.extend(variant.cages.iter().cloned());
+        self
+    }
+
+    /// Validates the grid like `validate`, also under the variant's rules, so a grid that's a valid
+    /// standard solution can still fail with `InvalidDiagonal` or `InvalidCage`.
+    pub fn validate_for_variant(
+        &self,
+        variant: &Variant,
+    ) -> Result<(), HashSet<InvalidSudokuError>> {
+        self.clone().with_variant(variant).validate()
+    }
+
+    /// Solves the grid under the variant's rules together, on top of any it already has. A puzzle
+    /// that each rule alone leaves solvable can still be unsolvable once they're combined.
+    pub fn solve_for_variant(&self, variant: &Variant) -> Result<Sudoku, InvalidSudokuError> {
+        self.clone().with_variant(variant).solve()
+    }
+
+    /// Turns on the X-Sudoku rule, where both main diagonals also need every number once. Solving,
+    /// possible numbers, and validation all take it into account.
+    pub fn with_diagonals(mut self) -> Self {
+        self.diagonals = true;
+        self
+    }
+
+    /// Gets the coords of the two main diagonals, top-left to bottom-right then top-right to
+    /// bottom-left.
+    pub(crate) fn diagonal_coords(&self) -> [Vec<Coord>; 2] {
+        let side = self.side();
+        [
+            (0..side).map(|i| Coord { row: i, col: i }).collect(),
+            (0..side)
+                .map(|i| Coord {
+                    row: i,
+                    col: side - 1 - i,
+                })
+                .collect(),
+        ]
+    }
+
+    /// Adds an `InvalidDiagonal` error for each diagonal with a repeated number, or for a
+    /// `complete` check, any diagonal that isn't every number once. Does nothing without
+    /// `with_diagonals`.
+    pub(crate) fn check_diagonals(&self, errors: &mut HashSet<InvalidSudokuError>, complete: bool) {
+        if !self.diagonals {
+            return;
+        }
+
+        for (i, diagonal) in self.diagonal_coords().into_iter().enumerate() {
+            let placed = diagonal
+                .iter()
+                .filter_map(|&coord| self.get(coord))
+                .collect::<Vec<u8>>();
+            let unique = placed.iter().collect::<HashSet<&u8>>().len();
+            if unique != placed.len() || (complete && unique != diagonal.len()) {
+                errors.insert(InvalidSudokuError::InvalidDiagonal(i as u8));
+            }
+        }
+    }
+
+    /// Gets the numbers the cell can be given the diagonals it's on, as a bitmask like
+    /// `possible_mask`. Every number is allowed without `with_diagonals`.
+    pub(crate) fn diagonal_mask(&self, coord: Coord) -> u32 {
+        if !self.diagonals {
+            return u32::MAX;
+        }
+
+        let used = self
+            .diagonal_coords()
+            .into_iter()
+            .filter(|diagonal| diagonal.contains(&coord))
+            .flatten()
+            .filter_map(|coord| self.get(coord))
+            .fold(0, |used, n| used | 1 << n);
+        !used
+    }
+
+    /// Adds a rule between two orthogonally adjacent cells, on top of the usual rules. Solving and
+    /// possible numbers take it into account, and `validate` checks it once both cells are filled.
+    pub fn constrain_adjacent(&mut self, a: Coord, b: Coord, rule: Adjacency) {
+        debug_assert_eq!(1, a.row.abs_diff(b.row) + a.col.abs_diff(b.col));
+        self.adjacency.push((a, b, rule));
+    }
+
+    /// Checks every adjacency rule with both of its cells filled, returning an error for each one
+    /// that's broken. Empty cells are fine, and a grid without any rules is always valid.
+    pub fn validate_adjacency(&self) -> Result<(), HashSet<InvalidSudokuError>> {
+        let errors = self
+            .adjacency
+            .iter()
+            .filter(|&&(a, b, rule)| match (self.get(a), self.get(b)) {
+                (Some(n), Some(m)) => rule.allowed_mask(n, self.side()) & (1 << m) == 0,
+                _ => false,
+            })
+            .map(|&(a, b, _)| InvalidSudokuError::InvalidAdjacency(a, b))
+            .collect::<HashSet<InvalidSudokuError>>();
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a killer sudoku cage, on top of the usual rules. Solving and possible numbers take it
+    /// into account, ruling out numbers that leave the rest of the cage unable to reach its sum.
+    pub fn add_cage(&mut self, cage: Cage) {
+        self.cages.push(cage);
+    }
+
+    /// Adds an `InvalidCage` error for each cage with a repeated number, or for a `complete` check,
+    /// any cage that isn't filled in adding up to its sum.
+    pub(crate) fn check_cages(&self, errors: &mut HashSet<InvalidSudokuError>, complete: bool) {
+        for (i, cage) in self.cages.iter().enumerate() {
+            let placed = cage.numbers(self).collect::<Vec<u8>>();
+            let unique = placed.iter().collect::<HashSet<&u8>>().len();
+            let sum = placed.iter().map(|&n| n as u32).sum::<u32>();
+            if unique != placed.len()
+                || (complete && (placed.len() != cage.cells.len() || sum != cage.sum as u32))
+            {
+                errors.insert(InvalidSudokuError::InvalidCage(i as u8));
+            }
+        }
+    }
+
+    /// Gets the numbers the cell can be given the cages it's in, as a bitmask like `possible_mask`.
+    /// A number is ruled out if it's already in the cage, or if the cage's other empty cells
+    /// couldn't make up the rest of the sum with the numbers that are left. A filled cell's cages
+    /// don't rule anything out.
+    pub(crate) fn cage_mask(&self, coord: Coord) -> u32 {
+        if self.get(coord).is_some() {
+            return u32::MAX;
+        }
+
+        let all = Sudoku::all_numbers(self.side());
+        self.cages
+            .iter()
+            .filter(|cage| cage.cells.contains(&coord))
+            .map(|cage| {
+                let used = cage.numbers(self).fold(0u32, |used, n| used | 1 << n);
+                let placed = cage.numbers(self).map(|n| n as u32).sum::<u32>();
+                let others = cage
+                    .cells
+                    .len()
+                    .saturating_sub(cage.numbers(self).count() + 1);
+                let remaining = (cage.sum as u32).saturating_sub(placed);
+
+                Sudoku::mask_numbers(all & !used)
+                    .filter(|&n| {
+                        // The smallest and largest sums the other empty cells could add up to.
+                        let left = Sudoku::mask_numbers(all & !used & !(1 << n))
+                            .map(|m| m as u32)
+                            .collect::<Vec<u32>>();
+                        if left.len() < others {
+                            return false;
+                        }
+                        let min = left[..others].iter().sum::<u32>();
+                        let max = left[left.len() - others..].iter().sum::<u32>();
+                        (n as u32 + min..=n as u32 + max).contains(&remaining)
+                    })
+                    .fold(0, |mask, n| mask | 1 << n)
+            })
+            .fold(u32::MAX, |mask, allowed| mask & allowed)
+    }
+
+    /// Checks the cages and adjacency rules could all hold before any solving, returning the first
+    /// problem found: a cage cell outside the grid or listed twice, a cage sum its size can't reach
+    /// with different numbers, an adjacency rule between cells that aren't neighbors, or the same
+    /// two cells given both rules. Jigsaw regions aren't attached to the grid, see
+    /// `validate_regions` for those.
+    pub fn validate_constraints(&self) -> Result<(), ConstraintError> {
+        let side = self.side();
+        let in_bounds = |coord: Coord| coord.row < side && coord.col < side;
+
+        for (i, cage) in self.cages.iter().enumerate() {
+            let i = i as u8;
+            let mut seen = HashSet::new();
+            for &coord in &cage.cells {
+                if !in_bounds(coord) {
+                    return Err(ConstraintError::CageCellOutOfBounds(i, coord));
+                }
+                if !seen.insert(coord) {
+                    return Err(ConstraintError::CageRepeatedCell(i, coord));
+                }
+            }
+
+            // The smallest and largest sums of that many different numbers, if there are that many.
+            let (size, side) = (cage.cells.len() as u32, side as u32);
+            let reachable = size <= side && {
+                let min = (1..=size).sum::<u32>();
+                let max = (side + 1 - size..=side).sum::<u32>();
+                (min..=max).contains(&(cage.sum as u32))
+            };
+            if !reachable {
+                return Err(ConstraintError::CageSumUnreachable {
+                    cage: i,
+                    sum: cage.sum,
+                });
+            }
+        }
+
+        for &(a, b, rule) in &self.adjacency {
+            if !in_bounds(a) || !in_bounds(b) || a.row.abs_diff(b.row) + a.col.abs_diff(b.col) != 1
+            {
+                return Err(ConstraintError::AdjacencyNotAdjacent(a, b));
+            }
+            let contradicts = self
+                .adjacency
+                .iter()
+                .any(|&(c, d, other)| ((c, d) == (a, b) || (c, d) == (b, a)) && other != rule);
+            if contradicts {
+                return Err(ConstraintError::ContradictoryAdjacency(a, b));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the numbers the cell can be under the adjacency rules given its filled neighbors, as a
+    /// bitmask like `possible_mask`.
+    pub(crate) fn adjacency_mask(&self, coord: Coord) -> u32 {
+        self.adjacency
+            .iter()
+            .filter_map(|&(a, b, rule)| match (a == coord, b == coord) {
+                (true, _) => Some((b, rule)),
+                (_, true) => Some((a, rule)),
+                _ => None,
+            })
+            .filter_map(|(other, rule)| Some(rule.allowed_mask(self.get(other)?, self.side())))
+            .fold(u32::MAX, |mask, allowed| mask & allowed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagonals() {
+        // Only the diagonals pin this one down, as a standard puzzle it has more than one solution.
+        let standard = include_str!("diagonal.txt").parse::<Sudoku>().unwrap();
+        assert_eq!(2, standard.count_solutions(2));
+
+        let sudoku = standard.clone().with_diagonals();
+        assert!(sudoku.has_unique_solution());
+        let solution = sudoku.solve().unwrap();
+        assert!(solution.validate().is_ok());
+        assert!(sudoku.solution_preserves_givens(&solution));
+
+        // The 6 and 4 further down the diagonal rule them out.
+        let coord = Coord { row: 1, col: 1 };
+        assert!(standard
+            .get_possible_numbers(coord)
+            .is_superset(&HashSet::from([4, 6])));
+        assert!(sudoku
+            .get_possible_numbers(coord)
+            .is_disjoint(&HashSet::from([4, 6])));
+
+        // A standard solution that repeats a number along a diagonal breaks it.
+        let base = Sudoku::new(vec![vec![None; 9]; 9]).solve().unwrap();
+        assert!(base.validate().is_ok());
+        let errors = base.with_diagonals().validate().unwrap_err();
+        assert!(errors.contains(&InvalidSudokuError::InvalidDiagonal(0)));
+    }
+
+    #[test]
+    fn test_validate_for_variant() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        assert!(solved.validate().is_ok());
+        assert!(solved.validate_for_variant(&Variant::default()).is_ok());
+
+        // A standard solution with a repeat along the main diagonal.
+        let diagonal = Variant {
+            diagonals: true,
+            ..Variant::default()
+        };
+        let errors = solved.validate_for_variant(&diagonal).unwrap_err();
+        assert!(errors.contains(&InvalidSudokuError::InvalidDiagonal(0)));
+        assert!(errors
+            .iter()
+            .all(|error| matches!(error, InvalidSudokuError::InvalidDiagonal(_))));
+        assert!(solved.validate().is_ok());
+
+        // The cage on the top-left two cells only holds for their actual sum.
+        let (a, b) = (Coord { row: 0, col: 0 }, Coord { row: 0, col: 1 });
+        let sum = solved.get(a).unwrap() + solved.get(b).unwrap();
+        let killer = |sum| Variant {
+            cages: vec![Cage {
+                cells: vec![a, b],
+                sum,
+            }],
+            ..Variant::default()
+        };
+        assert!(solved.validate_for_variant(&killer(sum)).is_ok());
+        assert_eq!(
+            Err(HashSet::from([InvalidSudokuError::InvalidCage(0)])),
+            solved.validate_for_variant(&killer(sum + 1))
+        );
+
+        // Cages added by the variant come after the grid's own.
+        let mut caged = solved.clone();
+        caged.add_cage(Cage {
+            cells: vec![a, b],
+            sum,
+        });
+        assert_eq!(
+            Err(HashSet::from([InvalidSudokuError::InvalidCage(1)])),
+            caged.validate_for_variant(&killer(sum + 1))
+        );
+    }
+
+    #[test]
+    fn test_solve_for_variant() {
+        // Only one of this puzzle's standard solutions works with the diagonals.
+        let sudoku = include_str!("diagonal.txt").parse::<Sudoku>().unwrap();
+        let diagonal = Variant {
+            diagonals: true,
+            ..Variant::default()
+        };
+        let x = sudoku.solve_for_variant(&diagonal).unwrap();
+        assert!(x.validate_for_variant(&diagonal).is_ok());
+        let other = sudoku.solutions().find(|solution| *solution != x).unwrap();
+        let coord = sudoku
+            .coords()
+            .find(|&coord| x.get(coord) != other.get(coord))
+            .unwrap();
+
+        // A one cell cage holding the other solution's number there.
+        let killer = Variant {
+            cages: vec![Cage {
+                cells: vec![coord],
+                sum: other.get(coord).unwrap(),
+            }],
+            ..Variant::default()
+        };
+        let caged = sudoku.solve_for_variant(&killer).unwrap();
+        assert_eq!(other.get(coord), caged.get(coord));
+        assert!(caged.validate_for_variant(&killer).is_ok());
+
+        // Each rule alone leaves a solution, but together they rule both out.
+        let mixed = Variant {
+            diagonals: true,
+            ..killer.clone()
+        };
+        assert_eq!(
+            Err(InvalidSudokuError::Unsolvable),
+            sudoku.solve_for_variant(&mixed)
+        );
+        assert_eq!(
+            Err(HashSet::from([InvalidSudokuError::InvalidCage(0)])),
+            x.validate_for_variant(&mixed)
+        );
+        assert!(caged
+            .validate_for_variant(&mixed)
+            .unwrap_err()
+            .iter()
+            .all(|error| matches!(error, InvalidSudokuError::InvalidDiagonal(_))));
+
+        // The cell's possible numbers are what both rules allow, not just the first one checked.
+        let combined = sudoku.clone().with_variant(&mixed);
+        let allowed = sudoku
+            .clone()
+            .with_variant(&diagonal)
+            .get_possible_numbers(coord)
+            .intersection(
+                &sudoku
+                    .clone()
+                    .with_variant(&killer)
+                    .get_possible_numbers(coord),
+            )
+            .copied()
+            .collect::<HashSet<u8>>();
+        assert_eq!(allowed, combined.get_possible_numbers(coord));
+    }
+
+    #[test]
+    fn test_adjacency() {
+        // The four cells form a rectangle of 6s and 7s that can be swapped, two solutions.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let mut sudoku = solved.clone();
+        for (row, col) in [(2, 1), (2, 2), (4, 1), (4, 2)] {
+            sudoku.unset(Coord { row, col });
+        }
+        assert_eq!(2, sudoku.count_solutions(3));
+
+        // The 5 to the right of (2, 2) makes the 6 the only consecutive choice there.
+        let (a, b) = (Coord { row: 2, col: 2 }, Coord { row: 2, col: 3 });
+        let mut consecutive = sudoku.clone();
+        consecutive.constrain_adjacent(a, b, Adjacency::Consecutive);
+        assert_eq!(1, consecutive.count_solutions(3));
+        assert_eq!(vec![6], consecutive.get_sorted_possible_numbers(a));
+
+        let solution = consecutive.solve().unwrap();
+        assert_eq!(Some(6), solution.get(a));
+        assert!(solution.validate_adjacency().is_ok());
+
+        let mut not_consecutive = sudoku.clone();
+        not_consecutive.constrain_adjacent(a, b, Adjacency::NotConsecutive);
+        assert_eq!(Some(7), not_consecutive.solve().unwrap().get(a));
+
+        // The original solution has a 7 there, which breaks the consecutive rule.
+        let mut broken = solved.clone();
+        broken.constrain_adjacent(a, b, Adjacency::Consecutive);
+        let expected = || HashSet::from([InvalidSudokuError::InvalidAdjacency(a, b)]);
+        assert_eq!(Err(expected()), broken.validate_adjacency());
+        assert_eq!(Err(expected()), broken.validate());
+
+        assert!(solved.validate_adjacency().is_ok());
+    }
+
+    #[test]
+    fn test_cages() {
+        // Same rectangle of 6s and 7s as `test_adjacency`, two solutions without a cage.
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let mut sudoku = solved.clone();
+        for (row, col) in [(2, 1), (2, 2), (4, 1), (4, 2)] {
+            sudoku.unset(Coord { row, col });
+        }
+        assert_eq!(2, sudoku.count_solutions(3));
+
+        // With the 5 next to it, only a 7 adds up to 12.
+        let cell = Coord { row: 2, col: 2 };
+        let cage = Cage {
+            cells: vec![cell, Coord { row: 2, col: 3 }],
+            sum: 12,
+        };
+        let mut killer = sudoku.clone();
+        killer.add_cage(cage.clone());
+        assert_eq!(1, killer.count_solutions(3));
+        assert_eq!(vec![7], killer.get_sorted_possible_numbers(cell));
+        let solution = killer.solve().unwrap();
+        assert_eq!(Some(7), solution.get(cell));
+        assert!(solution.validate().is_ok());
+
+        // Two empty cells that need 3 can only be a 1 and a 2.
+        let (a, b) = (Coord { row: 0, col: 0 }, Coord { row: 0, col: 1 });
+        let mut empty = Sudoku::new(vec![vec![None; 9]; 9]);
+        empty.add_cage(Cage {
+            cells: vec![a, b],
+            sum: 3,
+        });
+        assert_eq!(vec![1, 2], empty.get_sorted_possible_numbers(a));
+        let solution = empty.solve().unwrap();
+        assert!(solution.validate().is_ok());
+        assert_eq!(
+            Some(3),
+            solution.get(a).zip(solution.get(b)).map(|(n, m)| n + m)
+        );
+
+        // The original solution adds up to 12, a cage wanting 11 breaks it.
+        let mut broken = solved.clone();
+        broken.add_cage(Cage { sum: 11, ..cage });
+        let expected = HashSet::from([InvalidSudokuError::InvalidCage(0)]);
+        assert_eq!(Err(expected), broken.validate());
+        assert!(broken.check_valid().is_ok());
+
+        // A repeated number breaks a cage even before it's filled in, though the cells aren't peers.
+        let (a, b) = (Coord { row: 0, col: 0 }, Coord { row: 1, col: 4 });
+        let mut repeated = Sudoku::new(vec![vec![None; 9]; 9]);
+        repeated.set(a, 4);
+        repeated.set(b, 4);
+        assert!(repeated.check_valid().is_ok());
+        repeated.add_cage(Cage {
+            cells: vec![a, b],
+            sum: 8,
+        });
+        assert_eq!(
+            Err(InvalidSudokuError::DuplicateGiven),
+            repeated.solve().map(|_| ())
+        );
+
+        // A filled in cage doesn't get in the way of hints for the rest of the grid.
+        let mut filled = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        filled.set(Coord { row: 0, col: 0 }, 8);
+        filled.add_cage(Cage {
+            cells: vec![Coord { row: 0, col: 0 }, Coord { row: 0, col: 1 }],
+            sum: 17,
+        });
+        assert_eq!(u32::MAX, filled.cage_mask(Coord { row: 0, col: 1 }));
+        assert!(filled.hint().is_some());
+        assert_eq!(filled.empty_cells(), filled.solve_steps().count());
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let (a, b) = (Coord { row: 0, col: 0 }, Coord { row: 0, col: 1 });
+        let mut sudoku = Sudoku::new(vec![vec![None; 9]; 9]);
+        sudoku.add_cage(Cage {
+            cells: vec![a, b],
+            sum: 17,
+        });
+        sudoku.constrain_adjacent(a, b, Adjacency::NotConsecutive);
+        assert!(sudoku.validate_constraints().is_ok());
+
+        // Two different numbers add up to 17 at most, as 8 and 9.
+        let mut impossible = sudoku.clone();
+        impossible.add_cage(Cage {
+            cells: vec![Coord { row: 4, col: 4 }, Coord { row: 4, col: 5 }],
+            sum: 18,
+        });
+        let error = impossible.validate_constraints().unwrap_err();
+        assert_eq!(
+            ConstraintError::CageSumUnreachable { cage: 1, sum: 18 },
+            error
+        );
+        assert_eq!(
+            "cage 1 can't add up to 18 without repeating",
+            error.to_string()
+        );
+        assert!(impossible.solve().is_err());
+
+        // Ten cells can't all be different.
+        let mut oversized = Sudoku::new(vec![vec![None; 9]; 9]);
+        oversized.add_cage(Cage {
+            cells: (0..10)
+                .map(|i| Coord {
+                    row: i / 9,
+                    col: i % 9,
+                })
+                .collect(),
+            sum: 45,
+        });
+        assert_eq!(
+            Err(ConstraintError::CageSumUnreachable { cage: 0, sum: 45 }),
+            oversized.validate_constraints()
+        );
+
+        let mut repeated = Sudoku::new(vec![vec![None; 9]; 9]);
+        repeated.add_cage(Cage {
+            cells: vec![a, a],
+            sum: 3,
+        });
+        assert_eq!(
+            Err(ConstraintError::CageRepeatedCell(0, a)),
+            repeated.validate_constraints()
+        );
+
+        let mut contradictory = sudoku.clone();
+        contradictory.constrain_adjacent(b, a, Adjacency::Consecutive);
+        assert_eq!(
+            Err(ConstraintError::ContradictoryAdjacency(a, b)),
+            contradictory.validate_constraints()
+        );
+    }
+}
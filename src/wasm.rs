@@ -0,0 +1,51 @@
+use wasm_bindgen::prelude::*;
+
+use crate::Sudoku;
+
+/// Solves a puzzle given as a single line of 81 cells or as nine rows, returning the solution as a
+/// single line of 81 cells. Errors come back as readable messages.
+#[wasm_bindgen]
+pub fn solve_string(input: &str) -> Result<String, JsValue> {
+    solve_line(input).map_err(|error| JsValue::from_str(&error))
+}
+
+/// Does the work of `solve_string`, without anything that needs a browser.
+fn solve_line(input: &str) -> Result<String, String> {
+    let sudoku = input
+        .parse::<Sudoku>()
+        .map_err(|error| format!("couldn't parse puzzle: {error}"))?;
+    let solution = sudoku
+        .solve()
+        .map_err(|error| format!("couldn't solve puzzle: {error}"))?;
+
+    Ok(solution.to_line())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_line() {
+        let solved = include_str!("easy_solved.txt").parse::<Sudoku>().unwrap();
+        let expected = solved.to_line();
+        assert_eq!(Ok(expected.clone()), solve_line(include_str!("easy.txt")));
+
+        let easy = include_str!("easy.txt").parse::<Sudoku>().unwrap();
+        let line = easy.to_line();
+        assert_eq!(Ok(expected), solve_line(&line));
+
+        assert_eq!(
+            Err("couldn't parse puzzle: puzzle isn't 4x4, 9x9, or 16x16".to_string()),
+            solve_line("123")
+        );
+        let unsolvable = line.replacen('.', "9", 1);
+        assert_eq!(
+            Err(
+                "couldn't solve puzzle: puzzle has the same number twice in a row, col, or house"
+                    .to_string()
+            ),
+            solve_line(&unsolvable)
+        );
+    }
+}